@@ -0,0 +1,153 @@
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    sync::{Arc, LazyLock, OnceLock},
+};
+
+use tokio::sync::Mutex;
+use triggered::{Listener, Trigger};
+
+use crate::entities::TaskStatus;
+
+static SUBMISSIONS: LazyLock<Mutex<HashMap<String, AbortTrigger>>> =
+    LazyLock::new(Default::default);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AbortReason {
+    Timeout,
+    Cancelled,
+
+    /// A sibling of a parallel task with `fail_fast` has failed
+    SiblingFailed,
+}
+
+impl AbortReason {
+    /// The status of the tasks which have not completed when the submission is
+    /// aborted
+    pub fn task_status(self) -> TaskStatus {
+        match self {
+            Self::Timeout => TaskStatus::Timeout,
+            Self::Cancelled => TaskStatus::Cancelled,
+            Self::SiblingFailed => TaskStatus::Skipped,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AbortTrigger {
+    trigger: Trigger,
+    reason: Arc<OnceLock<AbortReason>>,
+}
+
+impl AbortTrigger {
+    /// Aborts the submission, only the first reason is kept when called
+    /// multiple times
+    pub fn abort(&self, reason: AbortReason) {
+        _ = self.reason.set(reason);
+        self.trigger.trigger();
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AbortListener {
+    listener: Listener,
+    reason: Arc<OnceLock<AbortReason>>,
+    parent: Option<Box<AbortListener>>,
+}
+
+impl AbortListener {
+    #[inline]
+    pub fn listener(&self) -> Listener {
+        self.listener.clone()
+    }
+
+    /// The reason of the parent signal takes precedence, since it applies to
+    /// the tasks aborted for their own reason as well
+    pub fn reason(&self) -> Option<AbortReason> {
+        self.parent
+            .as_ref()
+            .and_then(|parent| parent.reason())
+            .or_else(|| self.reason.get().copied())
+    }
+}
+
+pub fn abort_signal() -> (AbortTrigger, AbortListener) {
+    let (trigger, listener) = triggered::trigger();
+    let reason = Arc::new(OnceLock::new());
+    (AbortTrigger { trigger, reason: reason.clone() }, AbortListener {
+        listener,
+        reason,
+        parent: None,
+    })
+}
+
+/// Creates a signal for a part of the submission, which is not triggered by
+/// the parent signal itself but reports its reason once it is aborted
+pub fn child_abort_signal(parent: &AbortListener) -> (AbortTrigger, AbortListener) {
+    let (abort_tx, mut abort_rx) = abort_signal();
+    abort_rx.parent = Some(Box::new(parent.clone()));
+    (abort_tx, abort_rx)
+}
+
+/// Cancels an in-flight submission, returns false if no submission with the id
+/// is running
+pub async fn cancel_submission(id: &str) -> bool {
+    match SUBMISSIONS.lock().await.get(id) {
+        None => false,
+        Some(trigger) => {
+            trigger.abort(AbortReason::Cancelled);
+            true
+        }
+    }
+}
+
+/// Registers the trigger of a submission, returns false without replacing the
+/// existing one if a submission with the id is already registered
+pub(crate) async fn register_submission(id: &str, trigger: AbortTrigger) -> bool {
+    match SUBMISSIONS.lock().await.entry(id.to_owned()) {
+        Entry::Occupied(_) => false,
+        Entry::Vacant(entry) => {
+            entry.insert(trigger);
+            true
+        }
+    }
+}
+
+pub(crate) async fn unregister_submission(id: &str) {
+    SUBMISSIONS.lock().await.remove(id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AbortReason, abort_signal, child_abort_signal};
+
+    #[tokio::test]
+    async fn test_cancel_submission() {
+        assert!(!super::cancel_submission("test-cancel").await);
+
+        let (abort_tx, abort_rx) = abort_signal();
+        assert!(super::register_submission("test-cancel", abort_tx.clone()).await);
+        assert!(!super::register_submission("test-cancel", abort_signal().0).await);
+        assert!(super::cancel_submission("test-cancel").await);
+        abort_rx.listener().await;
+        assert_eq!(abort_rx.reason(), Some(AbortReason::Cancelled));
+
+        abort_tx.abort(AbortReason::Timeout);
+        assert_eq!(abort_rx.reason(), Some(AbortReason::Cancelled));
+
+        super::unregister_submission("test-cancel").await;
+        assert!(!super::cancel_submission("test-cancel").await);
+    }
+
+    #[test]
+    fn test_child_abort_signal() {
+        let (parent_tx, parent_rx) = abort_signal();
+        let (child_tx, child_rx) = child_abort_signal(&parent_rx);
+
+        child_tx.abort(AbortReason::SiblingFailed);
+        assert_eq!(child_rx.reason(), Some(AbortReason::SiblingFailed));
+        assert_eq!(parent_rx.reason(), None);
+
+        parent_tx.abort(AbortReason::Timeout);
+        assert_eq!(child_rx.reason(), Some(AbortReason::Timeout));
+    }
+}
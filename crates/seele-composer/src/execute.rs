@@ -1,24 +1,33 @@
-use std::{iter, path::PathBuf, sync::Arc};
+use std::{collections::HashMap, path::PathBuf, sync::Arc, time::Duration};
 
 use anyhow::{Context, Result, bail};
 use async_recursion::async_recursion;
 use either::Either;
-use futures_util::future;
+use futures_util::{StreamExt, future, stream};
+use indexmap::IndexMap;
 use ring_channel::RingSender;
 use tokio::{
     sync::{Mutex, oneshot},
-    time::Instant,
+    time::{Instant, sleep},
 };
-use tracing::{Span, debug, instrument};
+use tracing::{Span, debug, instrument, warn};
 
 use super::predicate;
 use crate::{
+    abort::{AbortListener, AbortReason, child_abort_signal},
+    interpolate::interpolate,
+    journal::Journal,
+    outputs::collect_outputs,
     report::apply_embeds_config,
-    shared::entities::{
-        ActionTaskConfig, ParallelFailedReport, ParallelSuccessReport, ParallelTaskConfig,
-        SequenceFailedReport, SequenceSuccessReport, Submission, SubmissionReportUploadConfig,
-        TaskConfig, TaskConfigExt, TaskEmbeds, TaskFailedReport, TaskNode, TaskNodeExt,
-        TaskReportWhenConfig, TaskStatus, TaskSuccessReport,
+    shared::{
+        entities::{
+            ActionAttemptReport, ActionFailedReport, ActionFailureReportExt, ActionReport,
+            ActionTaskConfig, ParallelFailedReport, ParallelSuccessReport, ParallelTaskConfig,
+            SequenceFailedReport, SequenceSuccessReport, Submission, SubmissionReportUploadConfig,
+            TaskConfig, TaskConfigExt, TaskEmbeds, TaskFailedReport, TaskNode, TaskNodeExt,
+            TaskOutputs, TaskReportWhenConfig, TaskRetryConfig, TaskStatus, TaskSuccessReport,
+        },
+        priority::Priority,
     },
     worker::{WorkerQueueItem, WorkerQueueTx},
 };
@@ -34,27 +43,49 @@ macro_rules! join_errors {
     };
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct ExecutionContext {
     submission_id: String,
     submission_root: PathBuf,
+    priority: Priority,
+    journal: Option<Arc<Journal>>,
     worker_queue_tx: WorkerQueueTx,
-    progress_tx: Mutex<RingSender<()>>,
-    upload_configs: Mutex<Vec<SubmissionReportUploadConfig>>,
+    abort: AbortListener,
+    progress_tx: Arc<Mutex<RingSender<()>>>,
+    upload_configs: Arc<Mutex<Vec<SubmissionReportUploadConfig>>>,
+    pending_parents: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl ExecutionContext {
+    /// Records that one of the node's predecessors has completed, returns
+    /// whether all of them have completed so the node is ready to be
+    /// checked
+    async fn complete_parent(&self, node: &TaskNode) -> bool {
+        let mut pending_parents = self.pending_parents.lock().await;
+        let count = pending_parents.entry(node.name.clone()).or_insert(node.parents.len());
+        *count -= 1;
+        *count == 0
+    }
 }
 
 #[instrument(skip_all)]
 pub async fn execute_submission(
     submission: Arc<Submission>,
+    journal: Option<Arc<Journal>>,
     worker_queue_tx: WorkerQueueTx,
+    abort: AbortListener,
     progress_tx: RingSender<()>,
 ) -> Result<Vec<SubmissionReportUploadConfig>> {
     let ctx = ExecutionContext {
         submission_id: submission.id.clone(),
         submission_root: submission.root_directory.clone(),
+        priority: submission.priority,
+        journal,
         worker_queue_tx,
-        progress_tx: Mutex::new(progress_tx),
-        upload_configs: Mutex::default(),
+        abort,
+        progress_tx: Arc::new(Mutex::new(progress_tx)),
+        upload_configs: Arc::default(),
+        pending_parents: Arc::default(),
     };
 
     let results = future::join_all(
@@ -67,11 +98,24 @@ pub async fn execute_submission(
         bail!("Execution got following internal error(s):\n{errors}");
     }
 
-    Ok(ctx.upload_configs.into_inner())
+    let upload_configs = ctx.upload_configs.lock().await;
+    Ok(upload_configs.clone())
+}
+
+/// Marks all the tasks of a submission which is aborted before its execution
+pub fn abort_submission(submission: &Submission, reason: AbortReason) {
+    for node in &submission.root_node.tasks {
+        set_task_node_status(node, reason.task_status());
+    }
 }
 
 #[async_recursion]
 async fn track_task_execution(ctx: &ExecutionContext, node: Arc<TaskNode>) -> Result<()> {
+    if let Some(reason) = ctx.abort.reason() {
+        set_task_node_status(&node, reason.task_status());
+        return Ok(());
+    }
+
     {
         *node.config.status.write().unwrap() = TaskStatus::Running;
     }
@@ -83,6 +127,16 @@ async fn track_task_execution(ctx: &ExecutionContext, node: Arc<TaskNode>) -> Re
         TaskNodeExt::Schedule(tasks) => track_schedule_execution(ctx, node.clone(), tasks).await?,
     };
 
+    if !node.config.outputs.is_empty()
+        && matches!(status, TaskStatus::Success { .. } | TaskStatus::Failed { .. })
+    {
+        *node.config.output_values.write().unwrap() =
+            match collect_outputs(&ctx.submission_root, &node.config.outputs, &status).await {
+                Err(err) => TaskOutputs::Error(format!("Error collecting outputs: {err:#}")),
+                Ok(values) => TaskOutputs::Values(values),
+            };
+    }
+
     if let Some(report) = &node.config.report {
         let success = matches!(status, TaskStatus::Success { .. });
 
@@ -124,17 +178,36 @@ async fn track_task_execution(ctx: &ExecutionContext, node: Arc<TaskNode>) -> Re
         _ = ctx.progress_tx.lock().await.send(());
     }
 
-    let (continue_nodes, skipped_nodes): (Vec<_>, Vec<_>) = node
-        .children
-        .iter()
-        .partition(|child_node| predicate::check_node_predicate(&node, child_node));
+    track_successors(ctx, &node).await
+}
 
-    for node in skipped_nodes {
-        skip_task_node(node);
+/// Checks the successors of a settled node, each of which is decided once all
+/// of its predecessors have settled, either executed or skipped
+#[async_recursion]
+async fn track_successors(ctx: &ExecutionContext, node: &TaskNode) -> Result<()> {
+    let mut ready_nodes = vec![];
+    for child_node in &node.children {
+        if ctx.complete_parent(child_node).await {
+            ready_nodes.push(child_node);
+        }
+    }
+
+    let (continue_nodes, skipped_nodes): (Vec<_>, Vec<_>) =
+        ready_nodes.into_iter().partition(|child_node| predicate::check_node_predicate(child_node));
+
+    // The successors of a skipped node are still checked, since they may run
+    // on a skipped predecessor or have other predecessors
+    for node in &skipped_nodes {
+        set_task_status(node, TaskStatus::Skipped);
     }
 
     let results = future::join_all(
-        continue_nodes.into_iter().map(|node| track_task_execution(ctx, node.clone())),
+        continue_nodes
+            .into_iter()
+            .map(|node| Either::Left(track_task_execution(ctx, node.clone())))
+            .chain(
+                skipped_nodes.into_iter().map(|node| Either::Right(track_successors(ctx, node))),
+            ),
     )
     .await;
     let errors = join_errors!(results);
@@ -151,21 +224,131 @@ async fn track_action_execution(
     node: Arc<TaskNode>,
     config: Arc<ActionTaskConfig>,
 ) -> Result<TaskStatus> {
-    debug!("Submitting the action");
-    let (tx, rx) = oneshot::channel();
-    ctx.worker_queue_tx
-        .send(WorkerQueueItem {
-            parent_span: Span::current(),
-            submission_root: ctx.submission_root.clone(),
-            submission_id: ctx.submission_id.clone(),
-            config,
-            report_tx: tx,
-        })
-        .await
-        .context("Failed to send the item")?;
+    if let Some(status) =
+        ctx.journal.as_ref().and_then(|journal| journal.completed_status(&node.name))
+    {
+        debug!("Reusing the status recorded before resuming");
+        return Ok(status);
+    }
+
+    let config = interpolate_step_outputs(&node, config)?;
+
+    if let Some(journal) = &ctx.journal {
+        if let Err(err) = journal.record_started(&node.name).await {
+            warn!("Error recording the action in the journal: {err:#}");
+        }
+    }
+
+    let mut previous_attempts = vec![];
+    loop {
+        debug!("Submitting the action");
+        let (tx, rx) = oneshot::channel();
+        ctx.worker_queue_tx
+            .send(ctx.priority, WorkerQueueItem {
+                parent_span: Span::current(),
+                submission_root: ctx.submission_root.clone(),
+                submission_id: ctx.submission_id.clone(),
+                priority: ctx.priority,
+                config: config.clone(),
+                abort: ctx.abort.listener(),
+                report_tx: tx,
+            })
+            .context("Failed to send the item")?;
+
+        let result = rx.await.context("Failed to receive the report")?;
+        // The error is usually caused by the abort, while a report produced
+        // before the worker noticed the abort is still kept
+        let abort_reason = ctx.abort.reason();
+        if let (Err(_), Some(reason)) = (&result, abort_reason) {
+            return Ok(reason.task_status());
+        }
 
-    let report = rx.await.context("Failed to receive the report")??;
-    Ok(report.into())
+        let retry = node.config.retry.as_ref().filter(|retry| {
+            abort_reason.is_none()
+                && previous_attempts.len() + 1 < retry.max_attempts
+                && should_retry(retry, &result)
+        });
+        let Some(retry) = retry else {
+            let mut report = result?;
+            match &mut report {
+                ActionReport::Success(report) => report.previous_attempts = previous_attempts,
+                ActionReport::Failed(report) => report.previous_attempts = previous_attempts,
+            }
+
+            let status = report.into();
+            if let Some(journal) = &ctx.journal {
+                if let Err(err) = journal.record_completed(&node.name, &status).await {
+                    warn!("Error recording the action in the journal: {err:#}");
+                }
+            }
+            return Ok(status);
+        };
+
+        let delay = retry_delay(retry, previous_attempts.len());
+        previous_attempts.push(match result {
+            Err(err) => ActionAttemptReport::Error { error: format!("{err:#}") },
+            Ok(ActionReport::Failed(report)) => ActionAttemptReport::Failed(report),
+            Ok(ActionReport::Success(_)) => unreachable!(),
+        });
+        warn!(
+            "Retrying the action in {}ms, attempt {}",
+            delay.as_millis(),
+            previous_attempts.len()
+        );
+
+        tokio::select! {
+            _ = sleep(delay) => {}
+            _ = ctx.abort.listener() => return Ok(ctx.abort.reason().unwrap().task_status()),
+        }
+    }
+}
+
+/// Substitutes the `${{ steps.<name>.outputs.<key> }}` placeholders in the
+/// action config with the outputs of the referenced tasks
+fn interpolate_step_outputs(
+    node: &TaskNode,
+    config: Arc<ActionTaskConfig>,
+) -> Result<Arc<ActionTaskConfig>> {
+    if node.steps.is_empty() {
+        return Ok(config);
+    }
+
+    let mut variables = IndexMap::new();
+    for (name, step) in &node.steps {
+        if let TaskOutputs::Values(values) = &*step.output_values.read().unwrap() {
+            for (key, value) in values {
+                variables.insert(format!("{name}.outputs.{key}"), serde_yaml::to_value(value)?);
+            }
+        }
+    }
+
+    let template = serde_yaml::to_value(&*config).context("Error serializing the action")?;
+    let mut value = template.clone();
+    interpolate(&mut value, "steps", &variables)
+        .context("Error interpolating the outputs of the steps")?;
+    if value == template {
+        return Ok(config);
+    }
+
+    Ok(Arc::new(serde_yaml::from_value(value).context("Error parsing the interpolated action")?))
+}
+
+fn should_retry(config: &TaskRetryConfig, result: &Result<ActionReport>) -> bool {
+    match result {
+        Err(_) => config.internal_error,
+        Ok(ActionReport::Failed(ActionFailedReport {
+            ext: ActionFailureReportExt::RunContainer(report),
+            ..
+        })) => config.statuses.contains(&report.status),
+        Ok(_) => false,
+    }
+}
+
+fn retry_delay(config: &TaskRetryConfig, retried_count: usize) -> Duration {
+    let factor = config.backoff_factor.powi(retried_count.try_into().unwrap_or(i32::MAX));
+    // The cast saturates when the delay grows out of range
+    let delay_ms = (config.backoff_ms as f64 * factor) as u64;
+    Duration::from_millis(delay_ms.min(config.max_backoff_ms))
 }
 
 #[instrument(skip_all, fields(task.name = node.name))]
@@ -175,8 +358,13 @@ async fn track_schedule_execution(
     tasks: &[Arc<TaskNode>],
 ) -> Result<TaskStatus> {
     let begin = Instant::now();
-    let results =
-        future::join_all(tasks.iter().cloned().map(|task| track_task_execution(ctx, task))).await;
+    let results = match &node.config.ext {
+        TaskConfigExt::Parallel(config) => execute_parallel_tasks(ctx, tasks, config).await,
+        _ => {
+            future::join_all(tasks.iter().cloned().map(|task| track_task_execution(ctx, task)))
+                .await
+        }
+    };
     let time_elapsed_ms = {
         let end = Instant::now();
         end.duration_since(begin).as_millis().try_into().unwrap()
@@ -197,6 +385,47 @@ async fn track_schedule_execution(
     })
 }
 
+/// Runs at most `max_concurrency` children at once. With `fail_fast`, the
+/// children which have not completed when a sibling fails are skipped.
+async fn execute_parallel_tasks(
+    ctx: &ExecutionContext,
+    tasks: &[Arc<TaskNode>],
+    config: &ParallelTaskConfig,
+) -> Vec<Result<()>> {
+    // The tasks are aborted along with their parent, and when one of them
+    // fails with `fail_fast`, so the running siblings are stopped as well
+    let parent_abort = &ctx.abort;
+    let (abort_tx, abort_rx) = child_abort_signal(parent_abort);
+    let ctx = &ExecutionContext { abort: abort_rx, ..ctx.clone() };
+
+    let max_concurrency = config.max_concurrency.unwrap_or(tasks.len()).max(1);
+    let execution = stream::iter(tasks.iter().cloned())
+        .map(|task| {
+            let abort_tx = &abort_tx;
+            async move {
+                let result = track_task_execution(ctx, task.clone()).await;
+                if config.fail_fast
+                    && (result.is_err()
+                        || matches!(*task.config.status.read().unwrap(), TaskStatus::Failed { .. }))
+                {
+                    abort_tx.abort(AbortReason::SiblingFailed);
+                }
+                result
+            }
+        })
+        .buffer_unordered(max_concurrency)
+        .collect();
+
+    tokio::pin!(execution);
+    tokio::select! {
+        results = &mut execution => results,
+        _ = parent_abort.listener() => {
+            abort_tx.abort(parent_abort.reason().unwrap());
+            execution.await
+        }
+    }
+}
+
 fn resolve_parallel_status(time_elapsed_ms: u64, config: &ParallelTaskConfig) -> TaskStatus {
     let mut status = TaskStatus::Success {
         report: TaskSuccessReport::Parallel(ParallelSuccessReport { time_elapsed_ms }),
@@ -208,26 +437,27 @@ fn resolve_parallel_status(time_elapsed_ms: u64, config: &ParallelTaskConfig) ->
                 status = TaskStatus::Pending;
             }
             TaskStatus::Failed { .. } => {
-                let failed_indexes = config
-                    .tasks
-                    .iter()
-                    .enumerate()
-                    .filter_map(|(index, task)| match *task.status.read().unwrap() {
-                        TaskStatus::Failed { .. } => Some(index),
-                        _ => None,
-                    })
-                    .collect::<Vec<_>>();
+                let (mut failed_indexes, mut skipped_indexes) = (vec![], vec![]);
+                for (index, task) in config.tasks.iter().enumerate() {
+                    match *task.status.read().unwrap() {
+                        TaskStatus::Failed { .. } => failed_indexes.push(index),
+                        TaskStatus::Skipped => skipped_indexes.push(index),
+                        _ => {}
+                    }
+                }
                 status = TaskStatus::Failed {
                     report: TaskFailedReport::Parallel(ParallelFailedReport {
                         time_elapsed_ms,
                         failed_count: failed_indexes.len(),
                         failed_indexes,
+                        skipped_indexes,
                     }),
                 };
                 break;
             }
-            TaskStatus::Skipped => {
-                panic!("Unexpected child status: Skipped")
+            ref aborted @ (TaskStatus::Timeout | TaskStatus::Cancelled) => {
+                status = aborted.clone();
+                break;
             }
             _ => {}
         }
@@ -256,8 +486,9 @@ fn resolve_sequence_status(
                 };
                 break;
             }
-            TaskStatus::Skipped => {
-                panic!("Unexpected child status: Skipped")
+            ref aborted @ (TaskStatus::Timeout | TaskStatus::Cancelled) => {
+                status = aborted.clone();
+                break;
             }
             _ => {}
         }
@@ -265,74 +496,143 @@ fn resolve_sequence_status(
     status
 }
 
-fn skip_task_node(node: &TaskNode) {
+/// Sets the status of the node and all of its subtasks
+fn set_task_status(node: &TaskNode, status: TaskStatus) {
     {
-        *node.config.status.write().unwrap() = TaskStatus::Skipped;
+        *node.config.status.write().unwrap() = status.clone();
     }
 
-    let nodes = (if let TaskNodeExt::Schedule(tasks) = &node.ext {
-        Either::Left(tasks.iter())
-    } else {
-        Either::Right(iter::empty())
-    })
-    .chain(node.children.iter());
-    for node in nodes {
-        {
-            *node.config.status.write().unwrap() = TaskStatus::Skipped;
+    if let TaskNodeExt::Schedule(tasks) = &node.ext {
+        for node in tasks {
+            set_task_node_status(node, status.clone());
         }
-        skip_task_node(node);
+    }
+}
+
+/// Sets the status of the node along with its subtasks and successors
+fn set_task_node_status(node: &TaskNode, status: TaskStatus) {
+    set_task_status(node, status.clone());
+    for node in &node.children {
+        set_task_node_status(node, status.clone());
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::{fs, num::NonZeroUsize, sync::Arc};
+    use std::{fs, num::NonZeroUsize, sync::Arc, time::Duration};
 
     use chrono::Utc;
     use insta::glob;
-    use tokio::{runtime::Builder, sync::mpsc};
+    use ring_channel::RingReceiver;
+    use tokio::{runtime::Builder, task::JoinHandle, time::sleep};
 
     use crate::{
-        entities::{ActionReport, ActionSuccessReport, ActionSuccessReportExt},
+        abort::{AbortReason, AbortTrigger, abort_signal},
+        entities::{
+            ActionAttemptReport, ActionFailedReport, ActionFailureReportExt, ActionReport,
+            ActionSuccessReport, ActionSuccessReportExt, ActionTaskConfig, Submission,
+            TaskConfigExt, TaskFailedReport, TaskRetryConfig, TaskStatus, TaskSuccessReport,
+        },
+        journal::Journal,
         resolve::resolve_submission,
-        worker::WorkerQueueItem,
+        shared::priority,
+        worker::{WorkerQueueItem, WorkerQueueRx},
     };
 
+    fn noop_report() -> anyhow::Result<ActionReport> {
+        noop_result(0, true)
+    }
+
+    /// A submission executed in the background, whose actions are sent to the
+    /// test through `worker_rx` instead of a worker
+    struct TestExecution {
+        submission: Arc<Submission>,
+        worker_rx: WorkerQueueRx,
+        abort_tx: AbortTrigger,
+        handle: JoinHandle<()>,
+        _progress_rx: RingReceiver<()>,
+    }
+
+    impl TestExecution {
+        fn start(config_yaml: &str, journal: Option<Arc<Journal>>) -> Self {
+            let submission = Arc::new(
+                resolve_submission(serde_yaml::from_str(config_yaml).unwrap(), "test".into())
+                    .expect("Error resolving the submission"),
+            );
+
+            let (worker_tx, worker_rx) = priority::channel("worker", Duration::ZERO);
+            let (progress_tx, progress_rx) =
+                ring_channel::ring_channel(NonZeroUsize::new(1).unwrap());
+            let (abort_tx, abort_rx) = abort_signal();
+            let handle = tokio::spawn({
+                let submission = submission.clone();
+                async move {
+                    super::execute_submission(
+                        submission,
+                        journal,
+                        worker_tx,
+                        abort_rx,
+                        progress_tx,
+                    )
+                    .await
+                    .unwrap();
+                }
+            });
+
+            Self { submission, worker_rx, abort_tx, handle, _progress_rx: progress_rx }
+        }
+
+        async fn recv(&mut self) -> WorkerQueueItem {
+            self.worker_rx.recv().await.expect("The submission has finished")
+        }
+
+        /// Waits for the submission to finish, no more actions are expected
+        async fn finish(mut self) -> Arc<Submission> {
+            assert!(self.worker_rx.recv().await.is_none());
+            self.handle.await.unwrap();
+            self.submission
+        }
+    }
+
+    fn statuses(submission: &Submission) -> Vec<TaskStatus> {
+        submission.config.tasks.values().map(|task| task.status.read().unwrap().clone()).collect()
+    }
+
+    fn noop_result(test: u64, success: bool) -> anyhow::Result<ActionReport> {
+        let ext = crate::shared::entities::noop::ExecutionReport { test };
+        Ok(if success {
+            ActionReport::Success(ActionSuccessReport {
+                run_at: Utc::now(),
+                time_elapsed_ms: 0,
+                previous_attempts: vec![],
+                ext: ActionSuccessReportExt::Noop(ext),
+            })
+        } else {
+            ActionReport::Failed(ActionFailedReport {
+                run_at: Utc::now(),
+                time_elapsed_ms: 0,
+                previous_attempts: vec![],
+                ext: ActionFailureReportExt::Noop(ext),
+            })
+        })
+    }
+
     #[test]
     fn test_execute_submission() {
         glob!("tests/*.yaml", |path| {
             Builder::new_multi_thread().enable_all().build().unwrap().block_on(async {
-                let submission = Arc::new(
-                    resolve_submission(
-                        serde_yaml::from_str(&fs::read_to_string(path).unwrap()).unwrap(),
-                        "test".into(),
-                    )
-                    .expect("Error resolving the submission"),
-                );
-
-                let (worker_tx, mut worker_rx) = mpsc::channel(114);
-                let (progress_tx, _progress_rx) =
-                    ring_channel::ring_channel(NonZeroUsize::new(1).unwrap());
-                let handle = tokio::spawn(async move {
-                    super::execute_submission(submission, worker_tx, progress_tx).await.unwrap();
-                });
+                let mut execution = TestExecution::start(&fs::read_to_string(path).unwrap(), None);
 
                 let mut results = vec![];
-                while let Some(WorkerQueueItem { config, report_tx, .. }) = worker_rx.recv().await {
+                while let Some(WorkerQueueItem { config, report_tx, .. }) =
+                    execution.worker_rx.recv().await
+                {
                     results.push(config);
 
-                    report_tx
-                        .send(Ok(ActionReport::Success(ActionSuccessReport {
-                            run_at: Utc::now(),
-                            time_elapsed_ms: 0,
-                            ext: ActionSuccessReportExt::Noop(
-                                crate::shared::entities::noop::ExecutionReport { test: 0 },
-                            ),
-                        })))
-                        .unwrap();
+                    report_tx.send(noop_report()).unwrap();
                 }
 
-                handle.await.unwrap();
+                execution.handle.await.unwrap();
 
                 insta::with_settings!({snapshot_path => "tests/snapshots"}, {
                     insta::assert_ron_snapshot!(results);
@@ -340,4 +640,329 @@ mod tests {
             });
         })
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_execute_submission_abort() {
+        let mut execution = TestExecution::start(
+            "steps:\n  one:\n    action: seele/noop@1\n    test: 1\n  two:\n    action: \
+             seele/noop@1\n    test: 2\n  three:\n    when: always\n    action: seele/noop@1\n    \
+             test: 3\n",
+            None,
+        );
+
+        let WorkerQueueItem { report_tx, .. } = execution.recv().await;
+        report_tx.send(noop_report()).unwrap();
+
+        // The second action only completes after the submission is aborted
+        let WorkerQueueItem { abort, report_tx, .. } = execution.recv().await;
+        execution.abort_tx.abort(AbortReason::Timeout);
+        abort.await;
+        report_tx.send(Err(anyhow::anyhow!(crate::shared::SUBMISSION_ABORTED_MESSAGE))).unwrap();
+
+        let submission = execution.finish().await;
+        assert!(matches!(statuses(&submission)[..], [
+            TaskStatus::Success { .. },
+            TaskStatus::Timeout,
+            TaskStatus::Timeout
+        ]));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_execute_submission_abort_completed() {
+        let mut execution = TestExecution::start(
+            "steps:\n  one:\n    action: seele/noop@1\n    test: 1\n  two:\n    action: \
+             seele/noop@1\n    test: 2\n",
+            None,
+        );
+
+        // The action completes before the worker notices the abort
+        let WorkerQueueItem { report_tx, .. } = execution.recv().await;
+        execution.abort_tx.abort(AbortReason::Timeout);
+        report_tx.send(noop_result(1, true)).unwrap();
+
+        let submission = execution.finish().await;
+        assert!(matches!(statuses(&submission)[..], [
+            TaskStatus::Success { .. },
+            TaskStatus::Timeout
+        ]));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_execute_submission_retry() {
+        let mut execution = TestExecution::start(
+            r#"
+            steps:
+              one:
+                action: seele/noop@1
+                test: 1
+                retry:
+                  max_attempts: 3
+                  backoff_ms: 1
+            "#,
+            None,
+        );
+
+        let WorkerQueueItem { report_tx, .. } = execution.recv().await;
+        report_tx.send(Err(anyhow::anyhow!("Transient error"))).unwrap();
+        let WorkerQueueItem { report_tx, .. } = execution.recv().await;
+        report_tx.send(noop_report()).unwrap();
+
+        let submission = execution.finish().await;
+        let status = submission.config.tasks[0].status.read().unwrap().clone();
+        let TaskStatus::Success { report: TaskSuccessReport::Action(report) } = status else {
+            panic!("Unexpected status: {status:?}");
+        };
+        assert!(matches!(
+            &report.previous_attempts[..],
+            [ActionAttemptReport::Error { error }] if error == "Transient error"
+        ));
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        let config: TaskRetryConfig = serde_yaml::from_str(
+            "{ max_attempts: 100, backoff_ms: 500, backoff_factor: 2, max_backoff_ms: 3000 }",
+        )
+        .unwrap();
+        let delays = [0, 1, 2, 3, 90].map(|count| super::retry_delay(&config, count).as_millis());
+        assert_eq!(delays, [500, 1000, 2000, 3000, 3000]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_execute_step_outputs() {
+        let mut execution = TestExecution::start(
+            r#"
+            steps:
+              first:
+                action: seele/noop@1
+                test: 1
+                outputs:
+                  n: { report: test }
+              second:
+                when: previous.outputs.n == 3
+                action: seele/add-file@1
+                files:
+                  - path: "${{ steps.first.outputs.n }}.txt"
+                    plain: test
+            "#,
+            None,
+        );
+
+        let WorkerQueueItem { report_tx, .. } = execution.recv().await;
+        report_tx.send(noop_result(3, true)).unwrap();
+        let WorkerQueueItem { config, report_tx, .. } = execution.recv().await;
+        report_tx.send(noop_report()).unwrap();
+
+        let submission = execution.finish().await;
+        let config = serde_json::to_value(&*config).unwrap();
+        assert_eq!(config["files"][0]["path"], "3.txt");
+        let first = serde_json::to_value(&submission.config.tasks[0]).unwrap();
+        assert_eq!(first["outputs"], serde_json::json!({ "n": 3 }));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_execute_parallel_fail_fast() {
+        let mut execution = TestExecution::start(
+            r#"
+            steps:
+              tests:
+                max_concurrency: 2
+                fail_fast: true
+                parallel:
+                  - action: seele/noop@1
+                    test: 1
+                  - action: seele/noop@1
+                    test: 2
+                  - action: seele/noop@1
+                    test: 3
+            "#,
+            None,
+        );
+
+        let WorkerQueueItem { report_tx: first_tx, abort, .. } = execution.recv().await;
+        let WorkerQueueItem { report_tx: second_tx, .. } = execution.recv().await;
+        sleep(Duration::from_millis(50)).await;
+        assert!(execution.worker_rx.try_recv().is_none());
+
+        // The running sibling is aborted as well
+        second_tx.send(noop_result(0, false)).unwrap();
+        abort.await;
+        first_tx.send(Err(anyhow::anyhow!(crate::shared::SUBMISSION_ABORTED_MESSAGE))).unwrap();
+
+        let submission = execution.finish().await;
+        let status = submission.config.tasks[0].status.read().unwrap().clone();
+        let TaskStatus::Failed { report: TaskFailedReport::Parallel(report) } = status else {
+            panic!("Unexpected status: {status:?}");
+        };
+        assert_eq!(report.failed_indexes, vec![1]);
+        assert_eq!(report.skipped_indexes, vec![0, 2]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_execute_parallel_fail_fast_unbounded() {
+        let mut execution = TestExecution::start(
+            r#"
+            steps:
+              tests:
+                fail_fast: true
+                parallel:
+                  - action: seele/noop@1
+                    test: 1
+                  - action: seele/noop@1
+                    test: 2
+              after:
+                when: always
+                action: seele/noop@1
+            "#,
+            None,
+        );
+
+        let mut items = vec![execution.recv().await, execution.recv().await];
+        items.sort_by_key(|item| match &*item.config {
+            ActionTaskConfig::Noop(config) => config.test,
+            _ => unreachable!(),
+        });
+        let [first, second] = <[_; 2]>::try_from(items).unwrap();
+
+        first.report_tx.send(noop_result(0, false)).unwrap();
+        second.abort.await;
+        second
+            .report_tx
+            .send(Err(anyhow::anyhow!(crate::shared::SUBMISSION_ABORTED_MESSAGE)))
+            .unwrap();
+
+        // Only the siblings are aborted, not the rest of the submission
+        let WorkerQueueItem { report_tx, .. } = execution.recv().await;
+        report_tx.send(noop_report()).unwrap();
+
+        let submission = execution.finish().await;
+        let status = submission.config.tasks[0].status.read().unwrap().clone();
+        let TaskStatus::Failed { report: TaskFailedReport::Parallel(report) } = status else {
+            panic!("Unexpected status: {status:?}");
+        };
+        assert_eq!(report.failed_indexes, vec![0]);
+        assert_eq!(report.skipped_indexes, vec![1]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_execute_parallel_fail_fast_timeout() {
+        let mut execution = TestExecution::start(
+            r#"
+            steps:
+              tests:
+                fail_fast: true
+                parallel:
+                  - action: seele/noop@1
+                    test: 1
+                  - action: seele/noop@1
+                    test: 2
+            "#,
+            None,
+        );
+
+        let mut items = vec![execution.recv().await, execution.recv().await];
+        items.sort_by_key(|item| match &*item.config {
+            ActionTaskConfig::Noop(config) => config.test,
+            _ => unreachable!(),
+        });
+        let [first, second] = <[_; 2]>::try_from(items).unwrap();
+
+        // The submission times out before the aborted sibling replies
+        first.report_tx.send(noop_result(0, false)).unwrap();
+        second.abort.await;
+        execution.abort_tx.abort(AbortReason::Timeout);
+        second
+            .report_tx
+            .send(Err(anyhow::anyhow!(crate::shared::SUBMISSION_ABORTED_MESSAGE)))
+            .unwrap();
+
+        let submission = execution.finish().await;
+        let TaskConfigExt::Parallel(config) = &submission.config.tasks[0].ext else {
+            unreachable!()
+        };
+        let statuses: Vec<_> =
+            config.tasks.iter().map(|task| task.status.read().unwrap().clone()).collect();
+        assert!(matches!(statuses[..], [TaskStatus::Failed { .. }, TaskStatus::Timeout]));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_execute_fan_in_skipped_parent() {
+        let mut execution = TestExecution::start(
+            r#"
+            steps:
+              prepare:
+                action: seele/noop@1
+                test: 1
+              skipped:
+                needs: prepare
+                when: "false"
+                action: seele/noop@1
+                test: 2
+              compile:
+                needs: prepare
+                action: seele/noop@1
+                test: 3
+              link:
+                needs: [skipped, compile]
+                when: always
+                action: seele/noop@1
+                test: 4
+              report:
+                needs: [skipped, compile]
+                action: seele/noop@1
+                test: 5
+            "#,
+            None,
+        );
+
+        let WorkerQueueItem { report_tx, .. } = execution.recv().await;
+        report_tx.send(noop_report()).unwrap();
+        let WorkerQueueItem { report_tx, .. } = execution.recv().await;
+        report_tx.send(noop_report()).unwrap();
+
+        // The fan-in task waits for the successful parent, not only the skipped one
+        let WorkerQueueItem { config, report_tx, .. } = execution.recv().await;
+        assert!(matches!(&*config, ActionTaskConfig::Noop(config) if config.test == 4));
+        report_tx.send(noop_report()).unwrap();
+
+        let submission = execution.finish().await;
+        assert!(matches!(statuses(&submission)[..], [
+            TaskStatus::Success { .. },
+            TaskStatus::Skipped,
+            TaskStatus::Success { .. },
+            TaskStatus::Success { .. },
+            TaskStatus::Skipped
+        ]));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_execute_submission_resume() {
+        let config_yaml = "steps:\n  one:\n    action: seele/noop@1\n    test: 1\n  two:\n    \
+                           action: seele/noop@1\n    test: 2\n";
+
+        // The first action has completed before the process restarts
+        let path =
+            std::env::temp_dir().join(format!("seele-journal-{:x}.jsonl", rand::random::<u64>()));
+        let journal = Journal::create(path.clone(), config_yaml).await.unwrap();
+        journal.record_started(".one").await.unwrap();
+        journal.record_completed(".one", &noop_report().unwrap().into()).await.unwrap();
+        journal.record_started(".two").await.unwrap();
+        drop(journal);
+
+        let (_, journal) = Journal::open(path).await.unwrap();
+        let journal = Arc::new(journal);
+        let mut execution = TestExecution::start(config_yaml, Some(journal.clone()));
+
+        let WorkerQueueItem { config, report_tx, .. } = execution.recv().await;
+        assert!(matches!(&*config, ActionTaskConfig::Noop(config) if config.test == 2));
+        report_tx.send(noop_report()).unwrap();
+
+        let submission = execution.finish().await;
+        assert!(matches!(statuses(&submission)[..], [
+            TaskStatus::Success { .. },
+            TaskStatus::Success { .. }
+        ]));
+
+        Arc::into_inner(journal).unwrap().remove().await.unwrap();
+    }
 }
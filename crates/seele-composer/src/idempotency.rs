@@ -0,0 +1,209 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::LazyLock,
+    time::Duration,
+};
+
+use ring_channel::RingSender;
+use tokio::{sync::Mutex, time::Instant};
+
+use crate::{
+    conf,
+    signal::{SubmissionSignal, SubmissionSignalExt},
+};
+
+static REGISTRY: LazyLock<Mutex<SubmissionRegistry>> = LazyLock::new(|| {
+    let config = &conf::CONFIG.composer.idempotency;
+    Mutex::new(SubmissionRegistry::new(config.result_capacity, config.result_ttl()))
+});
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Admission {
+    /// No submission with the id is running or has completed recently
+    Run,
+
+    /// The signals of the running submission are forwarded to the duplicate
+    Attached,
+
+    /// The final signal of the completed submission has been sent again
+    Replayed,
+}
+
+/// Tracks the submissions by their ids, so a duplicate submission, such as an
+/// AMQP redelivery, reuses the existing run instead of running again
+#[derive(Debug)]
+struct SubmissionRegistry {
+    in_flight: HashMap<String, Vec<RingSender<SubmissionSignal>>>,
+    results: HashMap<String, SubmissionSignal>,
+    result_order: VecDeque<(Instant, String)>,
+    result_capacity: usize,
+    result_ttl: Duration,
+}
+
+impl SubmissionRegistry {
+    fn new(result_capacity: usize, result_ttl: Duration) -> Self {
+        Self {
+            in_flight: HashMap::new(),
+            results: HashMap::new(),
+            result_order: VecDeque::new(),
+            result_capacity,
+            result_ttl,
+        }
+    }
+
+    fn admit(&mut self, id: &str, status_tx: &RingSender<SubmissionSignal>) -> Admission {
+        self.evict_expired_results();
+
+        if let Some(listeners) = self.in_flight.get_mut(id) {
+            listeners.push(status_tx.clone());
+            return Admission::Attached;
+        }
+
+        if let Some(signal) = self.results.get(id) {
+            _ = status_tx.send(signal.clone());
+            return Admission::Replayed;
+        }
+
+        self.in_flight.insert(id.to_owned(), vec![]);
+        Admission::Run
+    }
+
+    fn publish(&mut self, signal: &SubmissionSignal) {
+        let Some(listeners) = signal.id.as_ref().and_then(|id| self.in_flight.get_mut(id)) else {
+            return;
+        };
+
+        listeners.retain(|listener| listener.send(signal.clone()).is_ok());
+    }
+
+    fn finish(&mut self, signal: &SubmissionSignal) {
+        let Some(id) = &signal.id else {
+            return;
+        };
+
+        for listener in self.in_flight.remove(id).into_iter().flatten() {
+            _ = listener.send(signal.clone());
+        }
+
+        // Only the completed submissions are kept, the failed ones can succeed
+        // when being submitted again
+        if matches!(signal.ext, SubmissionSignalExt::Completed(_)) && self.result_capacity > 0 {
+            if self.results.insert(id.clone(), signal.clone()).is_some() {
+                self.result_order.retain(|(_, item)| item != id);
+            }
+            self.result_order.push_back((Instant::now(), id.clone()));
+
+            while self.result_order.len() > self.result_capacity {
+                if let Some((_, id)) = self.result_order.pop_front() {
+                    self.results.remove(&id);
+                }
+            }
+        }
+    }
+
+    fn evict_expired_results(&mut self) {
+        while let Some((completed_at, id)) = self.result_order.front() {
+            if completed_at.elapsed() < self.result_ttl {
+                break;
+            }
+
+            self.results.remove(id);
+            self.result_order.pop_front();
+        }
+    }
+}
+
+/// Checks whether the submission should run, always runs it unless the
+/// idempotency mode is enabled
+pub async fn admit_submission(id: &str, status_tx: &RingSender<SubmissionSignal>) -> Admission {
+    if !conf::CONFIG.composer.idempotency.enabled {
+        return Admission::Run;
+    }
+
+    REGISTRY.lock().await.admit(id, status_tx)
+}
+
+/// Forwards a progress signal to the duplicates attached to the submission
+pub async fn publish_signal(signal: &SubmissionSignal) {
+    if conf::CONFIG.composer.idempotency.enabled {
+        REGISTRY.lock().await.publish(signal);
+    }
+}
+
+/// Forwards the final signal to the duplicates attached to the submission and
+/// keeps it for the later duplicates if the submission has completed
+pub async fn finish_submission(signal: &SubmissionSignal) {
+    if conf::CONFIG.composer.idempotency.enabled {
+        REGISTRY.lock().await.finish(signal);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{num::NonZeroUsize, time::Duration};
+
+    use chrono::Utc;
+    use futures_util::StreamExt;
+    use ring_channel::ring_channel;
+    use serde_json::Value;
+    use tokio::time;
+
+    use super::{Admission, SubmissionRegistry};
+    use crate::signal::{
+        SubmissionErrorSignal, SubmissionReportSignal, SubmissionSignal, SubmissionSignalExt,
+    };
+
+    fn completed_signal(id: &str) -> SubmissionSignal {
+        SubmissionSignal {
+            id: Some(id.to_owned()),
+            ext: SubmissionSignalExt::Completed(SubmissionReportSignal {
+                report_at: Utc::now(),
+                report: None,
+                report_error: None,
+                report_logs: vec![],
+                timeout: false,
+                cached: false,
+                status: Value::Null,
+            }),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_submission_registry() {
+        let mut registry = SubmissionRegistry::new(1, Duration::from_secs(60));
+        let (status_tx, _status_rx) = ring_channel(NonZeroUsize::new(1).unwrap());
+        assert_eq!(registry.admit("first", &status_tx), Admission::Run);
+
+        let (duplicate_tx, mut duplicate_rx) = ring_channel(NonZeroUsize::new(1).unwrap());
+        assert_eq!(registry.admit("first", &duplicate_tx), Admission::Attached);
+        drop(duplicate_tx);
+
+        registry.finish(&completed_signal("first"));
+        let signal = duplicate_rx.next().await.unwrap();
+        assert_eq!(signal.ext.get_type(), "COMPLETED");
+        assert!(duplicate_rx.next().await.is_none());
+
+        let (replay_tx, mut replay_rx) = ring_channel(NonZeroUsize::new(1).unwrap());
+        assert_eq!(registry.admit("first", &replay_tx), Admission::Replayed);
+        assert_eq!(replay_rx.next().await.unwrap().id.as_deref(), Some("first"));
+
+        // The oldest result is evicted when the store is full
+        assert_eq!(registry.admit("second", &status_tx), Admission::Run);
+        registry.finish(&completed_signal("second"));
+        assert_eq!(registry.admit("first", &status_tx), Admission::Run);
+
+        // The result expires after the ttl
+        time::advance(Duration::from_secs(61)).await;
+        assert_eq!(registry.admit("second", &status_tx), Admission::Run);
+
+        // An error is forwarded but not kept
+        registry.finish(&SubmissionSignal {
+            id: Some("second".to_owned()),
+            ext: SubmissionSignalExt::Error(SubmissionErrorSignal {
+                error: "test".to_owned(),
+                parse_error: None,
+            }),
+        });
+        assert_eq!(registry.admit("second", &status_tx), Admission::Run);
+    }
+}
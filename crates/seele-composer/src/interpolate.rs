@@ -0,0 +1,278 @@
+use anyhow::{Context, Result, bail};
+use indexmap::IndexMap;
+use serde_yaml::Value;
+
+use crate::parse::{Segment, format_path};
+
+/// Substitutes the `${{ vars.<name> }}` placeholders in the steps of a
+/// submission config with the values defined in its top-level `vars`
+pub fn interpolate_submission_vars(config: &mut Value) -> Result<()> {
+    let Value::Mapping(config) = config else {
+        return Ok(());
+    };
+
+    // Without `vars`, the placeholders are still checked, so an undefined
+    // variable is reported with its path instead of being kept literally
+    let variables: IndexMap<String, Value> = match config.get("vars") {
+        None => IndexMap::new(),
+        Some(vars) => serde_yaml::from_value(vars.clone()).context("Error parsing the vars")?,
+    };
+
+    if let Some(steps) = config.get_mut("steps") {
+        interpolate_value(steps, "vars", &variables, &mut vec![Segment::Key("steps".into())])?;
+    }
+
+    Ok(())
+}
+
+/// Substitutes the `${{ <scope>.<name> }}` placeholders in the string values of
+/// `value`. A string consisting of a single placeholder is replaced by the
+/// variable itself, so its type is kept. Placeholders of other scopes are left
+/// untouched.
+pub fn interpolate(
+    value: &mut Value,
+    scope: &str,
+    variables: &IndexMap<String, Value>,
+) -> Result<()> {
+    interpolate_value(value, scope, variables, &mut vec![])
+}
+
+/// Names of the variables of the scope referenced by the placeholders in the
+/// string values of `value`
+pub fn references(value: &Value, scope: &str) -> Vec<String> {
+    let mut names = vec![];
+    collect_references(value, scope, &mut names);
+    names
+}
+
+fn collect_references(value: &Value, scope: &str, names: &mut Vec<String>) {
+    match value {
+        Value::String(text) => {
+            let mut rest = text.as_str();
+            while let Some(begin) = rest.find("${{") {
+                let Some(end) = rest[begin..].find("}}").map(|end| begin + end) else {
+                    break;
+                };
+                if let Some(name) = strip_scope(&rest[begin + 3..end], scope) {
+                    names.push(name.to_owned());
+                }
+                rest = &rest[end + 2..];
+            }
+        }
+        Value::Sequence(items) => {
+            items.iter().for_each(|item| collect_references(item, scope, names));
+        }
+        Value::Mapping(mapping) => {
+            mapping.values().for_each(|item| collect_references(item, scope, names));
+        }
+        Value::Tagged(tagged) => collect_references(&tagged.value, scope, names),
+        Value::Null | Value::Bool(_) | Value::Number(_) => {}
+    }
+}
+
+#[inline]
+fn strip_scope<'a>(expression: &'a str, scope: &str) -> Option<&'a str> {
+    expression.trim().strip_prefix(scope).and_then(|name| name.strip_prefix('.'))
+}
+
+fn interpolate_value(
+    value: &mut Value,
+    scope: &str,
+    variables: &IndexMap<String, Value>,
+    path: &mut Vec<Segment>,
+) -> Result<()> {
+    match value {
+        Value::String(text) => {
+            if let Some(result) = interpolate_str(text, scope, variables).with_context(|| {
+                format!("Error interpolating the value at `{}`", format_path(path))
+            })? {
+                *value = result;
+            }
+        }
+        Value::Sequence(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                path.push(Segment::Index(i));
+                interpolate_value(item, scope, variables, path)?;
+                path.pop();
+            }
+        }
+        Value::Mapping(mapping) => {
+            for (key, item) in mapping.iter_mut() {
+                path.push(Segment::Key(match key {
+                    Value::String(key) => key.clone(),
+                    key => serde_yaml::to_string(key)?.trim().to_owned(),
+                }));
+                interpolate_value(item, scope, variables, path)?;
+                path.pop();
+            }
+        }
+        Value::Tagged(tagged) => interpolate_value(&mut tagged.value, scope, variables, path)?,
+        Value::Null | Value::Bool(_) | Value::Number(_) => {}
+    }
+
+    Ok(())
+}
+
+fn interpolate_str(
+    text: &str,
+    scope: &str,
+    variables: &IndexMap<String, Value>,
+) -> Result<Option<Value>> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut changed = false;
+    while let Some(begin) = rest.find("${{") {
+        let Some(end) = rest[begin..].find("}}").map(|end| begin + end) else {
+            break;
+        };
+
+        let expression = rest[begin + 3..end].trim();
+        let Some(name) = strip_scope(expression, scope) else {
+            result.push_str(&rest[..end + 2]);
+            rest = &rest[end + 2..];
+            continue;
+        };
+
+        let Some(variable) = variables.get(name) else {
+            bail!("Unknown variable: {expression}");
+        };
+
+        if begin == 0 && end + 2 == rest.len() && !changed && result.is_empty() {
+            return Ok(Some(variable.clone()));
+        }
+
+        result.push_str(&rest[..begin]);
+        match variable {
+            Value::String(value) => result.push_str(value),
+            Value::Number(value) => result.push_str(&value.to_string()),
+            Value::Bool(value) => result.push_str(&value.to_string()),
+            Value::Null => {}
+            _ => bail!("Cannot interpolate the non-scalar variable {expression} into a string"),
+        }
+        rest = &rest[end + 2..];
+        changed = true;
+    }
+
+    if !changed {
+        return Ok(None);
+    }
+
+    result.push_str(rest);
+    Ok(Some(Value::String(result)))
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use serde_yaml::Value;
+
+    #[test]
+    fn test_interpolate() {
+        let variables: IndexMap<String, Value> =
+            serde_yaml::from_str("case: 1\nname: foo\nlist: [1]").unwrap();
+        let mut value: Value = serde_yaml::from_str(
+            r#"
+            test: ${{ matrix.case }}
+            files: ["${{matrix.case}}.in", "${{ matrix.name }}-${{ matrix.case }}.ans"]
+            other: ${{ vars.case }}
+            "#,
+        )
+        .unwrap();
+        super::interpolate(&mut value, "matrix", &variables).unwrap();
+
+        let expected: Value = serde_yaml::from_str(
+            r#"
+            test: 1
+            files: ["1.in", "foo-1.ans"]
+            other: ${{ vars.case }}
+            "#,
+        )
+        .unwrap();
+        assert_eq!(value, expected);
+
+        let mut value: Value = serde_yaml::from_str("files: [\"${{ matrix.missing }}\"]").unwrap();
+        let err = super::interpolate(&mut value, "matrix", &variables).unwrap_err();
+        assert!(format!("{err:#}").contains("`files[0]`"), "{err:#}");
+
+        let mut value: Value = serde_yaml::from_str("file: \"${{ matrix.list }}.in\"").unwrap();
+        assert!(super::interpolate(&mut value, "matrix", &variables).is_err());
+    }
+
+    #[test]
+    fn test_references() {
+        let value: Value = serde_yaml::from_str(
+            r#"
+            command: "${{ steps.detect.outputs.cc }} -o ${{ matrix.case }}"
+            files: ["${{steps.prepare.outputs.file}}"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(super::references(&value, "steps"), vec![
+            "detect.outputs.cc",
+            "prepare.outputs.file"
+        ]);
+    }
+
+    #[test]
+    fn test_interpolate_submission_vars() {
+        let mut config: Value = serde_yaml::from_str(
+            r#"
+            id: ${{ vars.image }}
+            vars:
+              image: gcc:11-bullseye
+              files: [main.c, main.h]
+            steps:
+              compile:
+                action: seele/run-judge/compile@1
+                run: { image: "${{ vars.image }}" }
+                files: ${{ vars.files }}
+                parallel:
+                  matrix: { case: [1] }
+                  task: { file: "${{ matrix.case }}.in" }
+            "#,
+        )
+        .unwrap();
+        super::interpolate_submission_vars(&mut config).unwrap();
+
+        let expected: Value = serde_yaml::from_str(
+            r#"
+            id: ${{ vars.image }}
+            vars:
+              image: gcc:11-bullseye
+              files: [main.c, main.h]
+            steps:
+              compile:
+                action: seele/run-judge/compile@1
+                run: { image: gcc:11-bullseye }
+                files: [main.c, main.h]
+                parallel:
+                  matrix: { case: [1] }
+                  task: { file: "${{ matrix.case }}.in" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config, expected);
+
+        let mut config: Value = serde_yaml::from_str(
+            r#"
+            vars: { image: gcc }
+            steps:
+              compile:
+                run: { files: [main.c, "${{ vars.source }}"] }
+            "#,
+        )
+        .unwrap();
+        let err = super::interpolate_submission_vars(&mut config).unwrap_err();
+        assert!(format!("{err:#}").contains("`steps.compile.run.files[1]`"), "{err:#}");
+
+        let mut config: Value = serde_yaml::from_str(
+            "steps:
+  compile:
+    run: { image: \"${{ vars.image }}\" }
+",
+        )
+        .unwrap();
+        let err = super::interpolate_submission_vars(&mut config).unwrap_err();
+        assert!(format!("{err:#}").contains("`steps.compile.run.image`"), "{err:#}");
+    }
+}
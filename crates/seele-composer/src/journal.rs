@@ -0,0 +1,294 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    fs::{self, File, OpenOptions},
+    io::AsyncWriteExt,
+    sync::Mutex,
+    time::sleep,
+};
+use tracing::{debug, info, warn};
+
+use crate::{
+    conf,
+    entities::{
+        ActionFailedReport, ActionSuccessReport, TaskFailedReport, TaskStatus, TaskSuccessReport,
+    },
+};
+
+/// The unfinished submissions found when starting up, keyed by their ids
+static RECOVERED: LazyLock<Mutex<HashMap<String, PathBuf>>> = LazyLock::new(Default::default);
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")]
+enum JournalRecord {
+    Submitted { config_yaml: String },
+    Started { name: String },
+    Succeeded { name: String, report: ActionSuccessReport },
+    Failed { name: String, report: ActionFailedReport },
+}
+
+/// An append-only log of a submission's config and the status transitions of
+/// its action tasks, which lets the submission resume after the process
+/// restarts
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    file: Mutex<File>,
+
+    /// The statuses of the action tasks completed before resuming
+    completed: HashMap<String, TaskStatus>,
+}
+
+impl Journal {
+    pub async fn create(path: PathBuf, config_yaml: &str) -> Result<Self> {
+        let file = File::create(&path)
+            .await
+            .with_context(|| format!("Error creating the journal: {}", path.display()))?;
+        let journal = Self { path, file: Mutex::new(file), completed: HashMap::new() };
+        journal.append(&JournalRecord::Submitted { config_yaml: config_yaml.to_owned() }).await?;
+        Ok(journal)
+    }
+
+    /// Opens an existing journal, returns it along with the config of the
+    /// submission
+    pub async fn open(path: PathBuf) -> Result<(String, Self)> {
+        let content = fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("Error reading the journal: {}", path.display()))?;
+
+        let mut config_yaml = None;
+        let mut completed = HashMap::new();
+        let lines = content.lines().collect::<Vec<_>>();
+        for (i, line) in lines.iter().enumerate() {
+            let record = match serde_json::from_str(line) {
+                Ok(record) => record,
+                // The last record can be partially written when the process dies
+                Err(_) if i + 1 == lines.len() => break,
+                Err(err) => bail!("Error parsing the record at line {}: {err:#}", i + 1),
+            };
+
+            match record {
+                JournalRecord::Submitted { config_yaml: yaml } => config_yaml = Some(yaml),
+                JournalRecord::Started { name } => {
+                    completed.remove(&name);
+                }
+                JournalRecord::Succeeded { name, report } => {
+                    completed.insert(name, TaskStatus::Success {
+                        report: TaskSuccessReport::Action(report),
+                    });
+                }
+                JournalRecord::Failed { name, report } => {
+                    completed.insert(name, TaskStatus::Failed {
+                        report: TaskFailedReport::Action(report),
+                    });
+                }
+            }
+        }
+        let Some(config_yaml) = config_yaml else {
+            bail!("The journal does not contain the submission config");
+        };
+
+        let file = OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .await
+            .with_context(|| format!("Error opening the journal: {}", path.display()))?;
+        Ok((config_yaml, Self { path, file: Mutex::new(file), completed }))
+    }
+
+    /// Returns the status of the action task if it has completed before
+    /// resuming
+    pub fn completed_status(&self, name: &str) -> Option<TaskStatus> {
+        self.completed.get(name).cloned()
+    }
+
+    pub async fn record_started(&self, name: &str) -> Result<()> {
+        self.append(&JournalRecord::Started { name: name.to_owned() }).await
+    }
+
+    /// Records the status of the action task, only successful and failed ones
+    /// are recorded since the others are never reused
+    pub async fn record_completed(&self, name: &str, status: &TaskStatus) -> Result<()> {
+        let record = match status {
+            TaskStatus::Success { report: TaskSuccessReport::Action(report) } => {
+                JournalRecord::Succeeded { name: name.to_owned(), report: report.clone() }
+            }
+            TaskStatus::Failed { report: TaskFailedReport::Action(report) } => {
+                JournalRecord::Failed { name: name.to_owned(), report: report.clone() }
+            }
+            _ => return Ok(()),
+        };
+        self.append(&record).await
+    }
+
+    pub async fn remove(self) -> Result<()> {
+        fs::remove_file(&self.path)
+            .await
+            .with_context(|| format!("Error removing the journal: {}", self.path.display()))
+    }
+
+    async fn append(&self, record: &JournalRecord) -> Result<()> {
+        let mut line = serde_json::to_vec(record).context("Error serializing the record")?;
+        line.push(b'\n');
+
+        let mut file = self.file.lock().await;
+        file.write_all(&line).await.context("Error writing the journal")?;
+        file.sync_data().await.context("Error syncing the journal")
+    }
+}
+
+#[inline]
+fn journal_path(id: &str) -> PathBuf {
+    conf::PATHS.journal.join(format!("{id}.jsonl"))
+}
+
+/// Detects the unfinished submissions left by the previous process, which
+/// are resumed when being submitted again before the timeout. The submission
+/// directories without a journal are removed.
+pub async fn recover_submissions() -> Result<()> {
+    let config = &conf::CONFIG.composer.journal;
+
+    let mut recovered = RECOVERED.lock().await;
+    let mut entries =
+        fs::read_dir(&conf::PATHS.journal).await.context("Error reading the journal directory")?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        let Some(id) = path.file_stem().and_then(|id| id.to_str()).map(ToOwned::to_owned) else {
+            continue;
+        };
+
+        if config.enabled && fs::metadata(conf::PATHS.submissions.join(&id)).await.is_ok() {
+            recovered.insert(id, path);
+        } else {
+            _ = fs::remove_file(&path).await;
+        }
+    }
+
+    let mut entries = fs::read_dir(&conf::PATHS.submissions)
+        .await
+        .context("Error reading the submissions directory")?;
+    while let Some(entry) = entries.next_entry().await? {
+        let Some(id) = entry.file_name().to_str().map(ToOwned::to_owned) else {
+            continue;
+        };
+
+        if !recovered.contains_key(&id) {
+            debug!(path = %entry.path().display(), "Removing the orphaned submission directory");
+            _ = fs::remove_dir_all(entry.path()).await;
+        }
+    }
+
+    if recovered.is_empty() {
+        return Ok(());
+    }
+
+    info!("Found {} unfinished submission(s) to resume", recovered.len());
+    tokio::spawn({
+        let timeout = config.resume_timeout();
+        async move {
+            sleep(timeout).await;
+
+            let mut recovered = RECOVERED.lock().await;
+            for (id, path) in recovered.drain() {
+                warn!("The unfinished submission {id} is not resumed in time, removing it");
+                _ = fs::remove_file(&path).await;
+                _ = fs::remove_dir_all(conf::PATHS.submissions.join(&id)).await;
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Resumes the journal of the unfinished submission if it has the same
+/// config, otherwise discards the unfinished submission
+pub async fn resume_journal(
+    id: &str,
+    config_yaml: &str,
+    submission_root: &Path,
+) -> Option<Journal> {
+    let path = RECOVERED.lock().await.remove(id)?;
+    match Journal::open(path.clone()).await {
+        Ok((recorded_yaml, journal)) if recorded_yaml == config_yaml => {
+            info!("Resuming the unfinished submission");
+            return Some(journal);
+        }
+        Ok(_) => warn!("Discarding the unfinished submission with a different config"),
+        Err(err) => warn!("Discarding the unfinished submission: {err:#}"),
+    }
+
+    _ = fs::remove_file(&path).await;
+    _ = fs::remove_dir_all(submission_root).await;
+    None
+}
+
+/// Creates the journal of the submission if the journal is enabled
+pub async fn create_journal(id: &str, config_yaml: &str) -> Result<Option<Journal>> {
+    if !conf::CONFIG.composer.journal.enabled {
+        return Ok(None);
+    }
+
+    Ok(Some(Journal::create(journal_path(id), config_yaml).await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use chrono::Utc;
+
+    use super::Journal;
+    use crate::entities::{
+        ActionSuccessReport, ActionSuccessReportExt, TaskStatus, TaskSuccessReport,
+    };
+
+    #[tokio::test]
+    async fn test_journal() {
+        let path = env::temp_dir().join(format!("seele-journal-{:x}.jsonl", rand::random::<u64>()));
+        let journal = Journal::create(path.clone(), "steps: {}").await.unwrap();
+
+        let status = TaskStatus::Success {
+            report: TaskSuccessReport::Action(ActionSuccessReport {
+                run_at: Utc::now(),
+                time_elapsed_ms: 1,
+                previous_attempts: vec![],
+                ext: ActionSuccessReportExt::Noop(crate::shared::entities::noop::ExecutionReport {
+                    test: 3,
+                }),
+            }),
+        };
+        for name in [".first", ".second", ".third"] {
+            journal.record_started(name).await.unwrap();
+        }
+        journal.record_completed(".first", &status).await.unwrap();
+        journal.record_completed(".second", &TaskStatus::Cancelled).await.unwrap();
+        journal.record_completed(".third", &status).await.unwrap();
+        // The third task is restarted after completing
+        journal.record_started(".third").await.unwrap();
+        drop(journal);
+
+        // A partially written record is ignored
+        let mut content = tokio::fs::read_to_string(&path).await.unwrap();
+        content.push_str(r#"{"type":"STA"#);
+        tokio::fs::write(&path, content).await.unwrap();
+
+        let (config_yaml, journal) = Journal::open(path.clone()).await.unwrap();
+        assert_eq!(config_yaml, "steps: {}");
+        assert!(matches!(
+            journal.completed_status(".first"),
+            Some(TaskStatus::Success { report: TaskSuccessReport::Action(report) })
+                if matches!(report.ext, ActionSuccessReportExt::Noop(ref report) if report.test == 3)
+        ));
+        assert!(journal.completed_status(".second").is_none());
+        assert!(journal.completed_status(".third").is_none());
+
+        journal.remove().await.unwrap();
+        assert!(tokio::fs::metadata(&path).await.is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use std::{num::NonZeroUsize, sync::Arc};
+use std::{num::NonZeroUsize, sync::Arc, time::Duration};
 
 use anyhow::{Context, Result, bail};
 use chrono::Utc;
@@ -13,29 +13,55 @@ use seele_worker as worker;
 use tokio::{
     fs,
     sync::mpsc::{self, error::TryRecvError},
-    time::Instant,
+    time::{Instant, sleep, sleep_until},
 };
 use tokio_graceful_shutdown::{FutureExt, SubsystemHandle};
-use tracing::{Span, debug, error, field, instrument};
-
-pub use self::signal::*;
+use tracing::{Span, debug, error, field, info, instrument, warn};
+
+pub use self::{
+    abort::cancel_submission,
+    parse::{SubmissionParseError, SubmissionVariantError},
+    retention::{
+        RetainedSubmission, SubmissionOutcome, archive_retained_submission,
+        list_retained_submissions,
+    },
+    schema::submission_schema,
+    signal::*,
+    validate::*,
+};
 use crate::{
-    entities::{Submission, SubmissionConfig},
+    abort::AbortReason,
+    entities::{Submission, SubmissionConfig, SubmissionReportUploadConfig},
+    idempotency::Admission,
+    parse::parse_submission,
     report::apply_uploads_config,
-    reporter::execute_reporter,
-    shared::metrics,
+    reporter::{ReporterOutput, execute_reporter},
+    shared::{
+        metrics,
+        priority::{self, Priority},
+    },
     worker::WorkerQueueTx,
 };
 
+mod abort;
 mod execute;
+mod idempotency;
+mod interpolate;
+mod journal;
+mod outputs;
+mod parse;
 mod predicate;
 mod report;
 mod reporter;
 mod resolve;
+mod result_cache;
+mod retention;
+mod schema;
 mod signal;
+mod validate;
 
-pub type ComposerQueueTx = mpsc::Sender<ComposerQueueItem>;
-pub type ComposerQueueRx = mpsc::Receiver<ComposerQueueItem>;
+pub type ComposerQueueTx = priority::PrioritySender<ComposerQueueItem>;
+pub type ComposerQueueRx = priority::PriorityReceiver<ComposerQueueItem>;
 
 const SUBMISSION_ID: &str = "seele.submission.id";
 const SUBMISSION_STATUS: &str = "seele.submission.status";
@@ -44,6 +70,11 @@ const SUBMISSION_ATTRIBUTE: &str = "seele.submission.attribute";
 #[derive(Debug)]
 pub struct ComposerQueueItem {
     pub config_yaml: String,
+
+    /// The priority given by the exchange, such as the priority of the message,
+    /// which is used unless the submission specifies its own
+    pub priority: Option<Priority>,
+
     pub status_tx: ring_channel::RingSender<SubmissionSignal>,
 }
 
@@ -52,8 +83,16 @@ pub async fn composer_main(
     mut composer_queue_rx: ComposerQueueRx,
     worker_queue_tx: WorkerQueueTx,
 ) -> Result<()> {
+    journal::recover_submissions().await.context("Error recovering the submissions")?;
+    retention::spawn_garbage_collector();
+
     while let Ok(Some(item)) = composer_queue_rx.recv().cancel_on_shutdown(&handle).await {
-        tokio::spawn(handle_submission(worker_queue_tx.clone(), item.config_yaml, item.status_tx));
+        tokio::spawn(handle_submission(
+            worker_queue_tx.clone(),
+            item.config_yaml,
+            item.priority,
+            item.status_tx,
+        ));
     }
 
     Ok(())
@@ -63,29 +102,48 @@ pub async fn composer_main(
 async fn handle_submission(
     worker_queue_tx: WorkerQueueTx,
     config_yaml: String,
+    priority: Option<Priority>,
     status_tx: RingSender<SubmissionSignal>,
 ) {
     let begin = Instant::now();
 
-    let submission = serde_yaml::from_str::<Arc<SubmissionConfig>>(&config_yaml);
-    let Ok(submission) = submission else {
-        let message = format!(
-            "Error parsing the submission: {:#}, partial content: {}",
-            submission.err().unwrap(),
-            config_yaml.as_str().truncate_ellipse(256)
-        );
-        error!(message);
+    let submission = match parse_submission(&config_yaml) {
+        Ok(submission) => submission,
+        Err(err) => {
+            let message = format!(
+                "Error parsing the submission: {:#}, partial content: {}",
+                err,
+                config_yaml.as_str().truncate_ellipse(256)
+            );
+            error!(message);
 
-        let ext = SubmissionSignalExt::Error(SubmissionErrorSignal { error: message });
-        Span::current().record(SUBMISSION_STATUS, ext.get_type());
+            let ext = SubmissionSignalExt::Error(SubmissionErrorSignal {
+                error: message,
+                parse_error: err.downcast_ref::<SubmissionParseError>().cloned(),
+            });
+            Span::current().record(SUBMISSION_STATUS, ext.get_type());
 
-        _ = status_tx.send(SubmissionSignal { id: None, ext });
-        return;
+            _ = status_tx.send(SubmissionSignal { id: None, ext });
+            return;
+        }
     };
 
     Span::current().record(SUBMISSION_ID, &submission.id);
     Span::current().record(SUBMISSION_ATTRIBUTE, &submission.tracing_attribute);
-    let signal_type = do_handle_submission(submission, worker_queue_tx, status_tx).await;
+
+    match idempotency::admit_submission(&submission.id, &status_tx).await {
+        Admission::Run => {}
+        Admission::Attached => {
+            info!("Attached to the running submission with the same id");
+            return;
+        }
+        Admission::Replayed => {
+            info!("Replayed the report of the completed submission with the same id");
+            return;
+        }
+    }
+    let signal_type =
+        do_handle_submission(submission, &config_yaml, priority, worker_queue_tx, status_tx).await;
     Span::current().record(SUBMISSION_STATUS, signal_type);
 
     let duration = {
@@ -99,8 +157,21 @@ async fn handle_submission(
     )]);
 }
 
+enum SubmissionResult {
+    /// The report of a submission with the same key in the result cache
+    Cached(Arc<result_cache::CachedReport>),
+
+    Executed {
+        status: serde_json::Value,
+        uploads: Vec<SubmissionReportUploadConfig>,
+        abort_reason: Option<AbortReason>,
+    },
+}
+
 async fn do_handle_submission(
     submission: Arc<SubmissionConfig>,
+    config_yaml: &str,
+    priority: Option<Priority>,
     worker_queue_tx: WorkerQueueTx,
     status_tx: RingSender<SubmissionSignal>,
 ) -> &'static str {
@@ -108,50 +179,143 @@ async fn do_handle_submission(
 
     let inner_submission = submission.clone();
     let inner_status_tx = status_tx.clone();
+    let mut journal = None;
+    let mut cache_key = None;
+
+    // Registered on admission, so the submission can be cancelled while it is
+    // being prepared as well
+    let (abort_tx, abort_rx) = abort::abort_signal();
+    let registered = abort::register_submission(&submission.id, abort_tx.clone()).await;
     let result = async {
-        if fs::metadata(&submission_root).await.is_ok() {
-            bail!(
-                "The submission's directory already exists, it may indicate a duplicate \
-                 submission id: {}",
-                submission_root.display()
-            );
+        if !registered {
+            bail!("A submission with the same id is already registered: {}", submission.id);
         }
 
-        fs::create_dir_all(&submission_root)
-            .await
-            .context("Error creating the submission directory")?;
-
         debug!("Resolving the submission");
-        let submission = Arc::new(
-            resolve::resolve_submission(inner_submission, submission_root.clone())
-                .context("Failed to resolve the submission")?,
-        );
+        let mut submission = resolve::resolve_submission(inner_submission, submission_root.clone())
+            .context("Failed to resolve the submission")?;
+        if let (None, Some(priority)) = (submission.config.priority, priority) {
+            submission.priority = priority;
+        }
+        let submission = Arc::new(submission);
+
+        if conf::CONFIG.composer.result_cache.enabled {
+            match result_cache::submission_key(config_yaml, &submission).await {
+                Err(err) => warn!("Error computing the key of the result cache: {err:#}"),
+                Ok(None) => debug!("Skipping the result cache for the images not pulled yet"),
+                Ok(Some(key)) => {
+                    if let Some(report) = result_cache::get(&key) {
+                        info!("Serving the submission from the result cache");
+                        return Ok(SubmissionResult::Cached(report));
+                    }
+                    cache_key = Some(key);
+                }
+            }
+        }
 
-        let (_abort_tx, abort_rx) = mpsc::channel(1);
+        // Only prepared after the cache lookup, which saves the work for a
+        // cache hit
+        let resumed = journal::resume_journal(&submission.id, config_yaml, &submission_root).await;
+        if let Some(resumed) = resumed {
+            journal = Some(Arc::new(resumed));
+        } else {
+            if fs::metadata(&submission_root).await.is_ok() {
+                bail!(
+                    "The submission's directory already exists, it may indicate a duplicate \
+                     submission id: {}",
+                    submission_root.display()
+                );
+            }
+
+            fs::create_dir_all(&submission_root)
+                .await
+                .context("Error creating the submission directory")?;
+
+            journal = journal::create_journal(&submission.id, config_yaml)
+                .await
+                .context("Error creating the journal")?
+                .map(Arc::new);
+        }
+
+        if let Some(reason) = abort_rx.reason() {
+            debug!("The submission was aborted before its execution");
+            execute::abort_submission(&submission, reason);
+            let status = serde_json::to_value(&submission.config)
+                .context("Error serializing the submission report")?;
+            return Ok(SubmissionResult::Executed {
+                status,
+                uploads: vec![],
+                abort_reason: Some(reason),
+            });
+        }
+
+        let (_progress_stop_tx, progress_stop_rx) = mpsc::channel(1);
         let (progress_tx, progress_rx) = ring_channel::ring_channel(NonZeroUsize::new(1).unwrap());
         tokio::spawn({
             let span = Span::current();
             let submission = submission.clone();
-            handle_progress_report(span, submission, abort_rx, progress_rx, inner_status_tx)
+            handle_progress_report(span, submission, progress_stop_rx, progress_rx, inner_status_tx)
         });
 
         debug!("Executing the submission");
-        let uploads = execute::execute_submission(submission.clone(), worker_queue_tx, progress_tx)
-            .await
-            .context("Error executing the submission")?;
+        let result = {
+            let execution = execute::execute_submission(
+                submission.clone(),
+                journal.clone(),
+                worker_queue_tx,
+                abort_rx.clone(),
+                progress_tx,
+            );
+            match submission.config.timeout_ms {
+                None => execution.await,
+                Some(timeout_ms) => {
+                    tokio::pin!(execution);
+                    tokio::select! {
+                        result = &mut execution => result,
+                        _ = sleep(Duration::from_millis(timeout_ms)) => {
+                            debug!("The submission has reached its deadline, aborting");
+                            abort_tx.abort(AbortReason::Timeout);
+                            execution.await
+                        }
+                    }
+                }
+            }
+        };
+        let uploads = result.context("Error executing the submission")?;
 
         let status = serde_json::to_value(&submission.config)
             .context("Error serializing the submission report")?;
-        Ok((status, uploads))
+        Ok(SubmissionResult::Executed { status, uploads, abort_reason: abort_rx.reason() })
     }
     .await;
+    if registered {
+        abort::unregister_submission(&submission.id).await;
+    }
 
     let (ext, uploads) = match result {
         Err(err) => {
             error!("Error handling the submission: {err:#}");
-            (SubmissionSignalExt::Error(SubmissionErrorSignal { error: format!("{err:#}") }), None)
+            (
+                SubmissionSignalExt::Error(SubmissionErrorSignal {
+                    error: format!("{err:#}"),
+                    parse_error: None,
+                }),
+                None,
+            )
         }
-        Ok((status, mut uploads)) => {
+        Ok(SubmissionResult::Cached(report)) => {
+            let signal = SubmissionReportSignal {
+                report_at: Utc::now(),
+                status: report.status.clone(),
+                report: report.report.clone(),
+                report_error: None,
+                report_logs: vec![],
+                timeout: false,
+                cached: true,
+            };
+            (SubmissionSignalExt::Completed(signal), None)
+        }
+        Ok(SubmissionResult::Executed { status, mut uploads, abort_reason }) => {
             let result = match &submission.reporter {
                 None => None,
                 Some(reporter) => {
@@ -159,33 +323,49 @@ async fn do_handle_submission(
                 }
             };
 
-            let (report, report_error) = match result {
-                None => (None, None),
-                Some(Ok((report, reporter_uploads))) => {
+            let (report, report_error, report_logs) = match result {
+                None => (None, None, vec![]),
+                Some(ReporterOutput { result: Ok((report, reporter_uploads)), logs }) => {
                     uploads.extend(reporter_uploads);
-                    (Some(report), None)
+                    (Some(report), None, logs)
                 }
-                Some(Err(err)) => {
+                Some(ReporterOutput { result: Err(err), logs }) => {
                     error!("The reporter failed: {err:#}");
-                    (None, Some(format!("{err:#}")))
+                    (None, Some(format!("{err:#}")), logs)
                 }
             };
 
-            (
-                SubmissionSignalExt::Completed(SubmissionReportSignal {
-                    report_at: Utc::now(),
-                    status,
-                    report,
-                    report_error,
-                }),
-                Some(uploads),
-            )
+            let signal = SubmissionReportSignal {
+                report_at: Utc::now(),
+                status,
+                report,
+                report_error,
+                report_logs,
+                timeout: abort_reason == Some(AbortReason::Timeout),
+                cached: false,
+            };
+
+            // The files to upload are not kept, so a cache hit could not upload them
+            if let (None, None, Some(key), true) =
+                (abort_reason, &signal.report_error, cache_key, uploads.is_empty())
+            {
+                result_cache::write(key, signal.status.clone(), signal.report.clone());
+            }
+
+            let ext = match abort_reason {
+                Some(AbortReason::Cancelled) => SubmissionSignalExt::Cancelled(signal),
+                _ => SubmissionSignalExt::Completed(signal),
+            };
+            (ext, Some(uploads))
         }
     };
     let signal_type = ext.get_type();
+    let outcome = SubmissionOutcome::of(&submission, &ext);
 
     debug!("Sending the final submission signal");
-    _ = status_tx.send(SubmissionSignal { id: Some(submission.id.clone()), ext });
+    let signal = SubmissionSignal { id: Some(submission.id.clone()), ext };
+    idempotency::finish_submission(&signal).await;
+    _ = status_tx.send(signal);
 
     if let Some(uploads) = uploads {
         debug!("Handling file uploads");
@@ -194,7 +374,26 @@ async fn do_handle_submission(
         }
     }
 
-    _ = fs::remove_dir_all(submission_root).await;
+    // The directory belongs to the registered submission with the same id, and
+    // is not created if the submission is served from the cache or fails to
+    // resolve
+    if registered && fs::try_exists(&submission_root).await.unwrap_or(false) {
+        let retained = match outcome {
+            None => false,
+            Some(outcome) => {
+                retention::retain_submission(&submission.id, &submission_root, outcome).await
+            }
+        };
+        if !retained {
+            _ = fs::remove_dir_all(submission_root).await;
+        }
+    }
+
+    if let Some(journal) = journal.and_then(Arc::into_inner) {
+        if let Err(err) = journal.remove().await {
+            error!("Error removing the journal: {err:#}");
+        }
+    }
 
     signal_type
 }
@@ -207,44 +406,55 @@ async fn handle_progress_report(
     mut progress_rx: RingReceiver<()>,
     status_tx: RingSender<SubmissionSignal>,
 ) {
+    let interval = conf::CONFIG.composer.reporter.progress_interval();
+    let reporter =
+        submission.config.progress_reporter.as_ref().or(submission.config.reporter.as_ref());
+    let mut last_report_at = None;
     loop {
         tokio::select! {
             _ = abort_rx.recv() => break,
             item = progress_rx.next() => match item {
                 None => break,
                 Some(_) => {
+                    // The status is serialized after waiting, so the progress
+                    // made in the meantime is included in this report
+                    if let Some(last_report_at) = last_report_at {
+                        tokio::select! {
+                            _ = abort_rx.recv() => break,
+                            _ = sleep_until(last_report_at + interval) => {}
+                        }
+                    }
+                    last_report_at = Some(Instant::now());
+
                     let result = async {
                         let signal = {
                             let report_at = Utc::now();
                             let status = serde_json::to_value(&submission.config).context("Error serializing the submission report")?;
                             SubmissionSignal {
                                 id: Some(submission.id.clone()),
-                                ext: SubmissionSignalExt::Progress(match &submission.config.reporter {
-                                    None => SubmissionReportSignal {
-                                        report_at,
-                                        report: None,
-                                        report_error: None,
-                                        status
-                                    },
-                                    Some(reporter) => match execute_reporter(&submission.root_directory, reporter, status.clone()).await {
-                                        Ok((report, _)) => SubmissionReportSignal {
-                                            report_at,
-                                            report: Some(report),
-                                            report_error: None,
-                                            status
-                                        },
-                                        Err(err) => SubmissionReportSignal {
-                                            report_at,
-                                            report: None,
-                                            report_error: Some(format!("{err:#}")),
-                                            status,
+                                ext: SubmissionSignalExt::Progress({
+                                    let (report, report_error, report_logs) = match reporter {
+                                        None => (None, None, vec![]),
+                                        Some(reporter) => match execute_reporter(&submission.root_directory, reporter, status.clone()).await {
+                                            ReporterOutput { result: Ok((report, _)), logs } => (Some(report), None, logs),
+                                            ReporterOutput { result: Err(err), logs } => (None, Some(format!("{err:#}")), logs),
                                         },
+                                    };
+                                    SubmissionReportSignal {
+                                        report_at,
+                                        report,
+                                        report_error,
+                                        report_logs,
+                                        timeout: false,
+                                        cached: false,
+                                        status,
                                     }
                                 })
                             }
                         };
 
                         if matches!(abort_rx.try_recv(), Err(TryRecvError::Empty)) {
+                            idempotency::publish_signal(&signal).await;
                             _ = status_tx.send(signal);
                         }
 
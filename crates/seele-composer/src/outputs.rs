@@ -0,0 +1,154 @@
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use indexmap::IndexMap;
+use serde_json::Value;
+use tokio::fs;
+
+use crate::{
+    entities::{TaskOutputConfig, TaskStatus},
+    shared::file::resolve_path,
+};
+
+const MAX_OUTPUT_FILE_SIZE_BYTES: u64 = 64 * 1024;
+
+pub async fn collect_outputs(
+    root: &Path,
+    outputs: &IndexMap<String, TaskOutputConfig>,
+    status: &TaskStatus,
+) -> Result<IndexMap<String, Value>> {
+    let mut values = IndexMap::with_capacity(outputs.len());
+    for (name, config) in outputs {
+        let value = collect_output(root, config, status)
+            .await
+            .with_context(|| format!("Error collecting the output {name}"))?;
+        values.insert(name.clone(), value);
+    }
+
+    Ok(values)
+}
+
+async fn collect_output(
+    root: &Path,
+    config: &TaskOutputConfig,
+    status: &TaskStatus,
+) -> Result<Value> {
+    Ok(match config {
+        TaskOutputConfig::File { file } => {
+            Value::String(read_file(&resolve_path(root, file)?).await?.trim_end().to_owned())
+        }
+        TaskOutputConfig::Json { json, field } => {
+            let document = serde_json::from_str(&read_file(&resolve_path(root, json)?).await?)
+                .context("Error parsing the JSON file")?;
+            read_field(document, field)?
+        }
+        TaskOutputConfig::Report { report } => {
+            let mut value = serde_json::to_value(status).context("Error serializing the status")?;
+            read_field(value["report"].take(), report)?
+        }
+    })
+}
+
+async fn read_file(path: &Path) -> Result<String> {
+    let metadata = fs::metadata(path)
+        .await
+        .with_context(|| format!("Error opening the file: {}", path.display()))?;
+    if metadata.len() > MAX_OUTPUT_FILE_SIZE_BYTES {
+        bail!(
+            "The file exceeds the size limit of {MAX_OUTPUT_FILE_SIZE_BYTES} bytes: {}",
+            path.display()
+        );
+    }
+
+    fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Error reading the file: {}", path.display()))
+}
+
+/// Reads the field specified by the dot-separated path, the value itself is
+/// returned if the path is empty
+fn read_field(mut value: Value, path: &str) -> Result<Value> {
+    for segment in path.split('.').filter(|segment| !segment.is_empty()) {
+        value = match value {
+            Value::Object(mut map) => map.remove(segment),
+            Value::Array(mut items) => segment
+                .parse::<usize>()
+                .ok()
+                .filter(|&index| index < items.len())
+                .map(|index| items.swap_remove(index)),
+            _ => None,
+        }
+        .with_context(|| format!("The field does not exist: {path}"))?;
+    }
+
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use chrono::Utc;
+    use indexmap::IndexMap;
+    use serde_json::json;
+
+    use crate::entities::{
+        ActionSuccessReport, ActionSuccessReportExt, TaskOutputConfig, TaskStatus,
+        TaskSuccessReport,
+    };
+
+    #[tokio::test]
+    async fn test_collect_outputs() {
+        let root = env::temp_dir().join(format!("seele-outputs-{:x}", rand::random::<u64>()));
+        tokio::fs::create_dir_all(&root).await.unwrap();
+        tokio::fs::write(root.join("lang.txt"), "cpp\n").await.unwrap();
+        tokio::fs::write(root.join("checker.json"), r#"{"score": {"total": 80}, "cases": [1, 2]}"#)
+            .await
+            .unwrap();
+
+        let status = TaskStatus::Success {
+            report: TaskSuccessReport::Action(ActionSuccessReport {
+                run_at: Utc::now(),
+                time_elapsed_ms: 0,
+                previous_attempts: vec![],
+                ext: ActionSuccessReportExt::Noop(crate::shared::entities::noop::ExecutionReport {
+                    test: 3,
+                }),
+            }),
+        };
+        let outputs: IndexMap<String, TaskOutputConfig> = serde_yaml::from_str(
+            r#"
+            lang: { file: lang.txt }
+            score: { json: checker.json, field: score.total }
+            second: { json: checker.json, field: cases.1 }
+            test: { report: test }
+            "#,
+        )
+        .unwrap();
+        let values = super::collect_outputs(&root, &outputs, &status).await.unwrap();
+        assert_eq!(json!(values), json!({"lang": "cpp", "score": 80, "second": 2, "test": 3}));
+
+        for output in ["{ file: missing.txt }", "{ json: lang.txt }", "{ report: missing }"] {
+            let outputs: IndexMap<String, TaskOutputConfig> =
+                serde_yaml::from_str(&format!("output: {output}")).unwrap();
+            let result = super::collect_outputs(&root, &outputs, &status).await;
+            assert!(result.is_err(), "case {output}");
+        }
+
+        // The files must be inside the submission root, even if the path leads
+        // back into it
+        let root_name = root.file_name().unwrap().to_str().unwrap();
+        for output in [
+            "{ file: /etc/passwd }".to_owned(),
+            format!("{{ file: ../{root_name}/lang.txt }}"),
+            format!("{{ json: ../{root_name}/checker.json }}"),
+        ] {
+            let outputs: IndexMap<String, TaskOutputConfig> =
+                serde_yaml::from_str(&format!("output: {output}")).unwrap();
+            let result = super::collect_outputs(&root, &outputs, &status).await;
+            assert!(result.is_err(), "case {output}");
+        }
+
+        tokio::fs::remove_dir_all(&root).await.unwrap();
+    }
+}
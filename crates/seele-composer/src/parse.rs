@@ -0,0 +1,585 @@
+use std::{
+    fmt::{self, Display},
+    sync::{Arc, LazyLock},
+};
+
+use anyhow::Result;
+use serde::{
+    Serialize,
+    de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor},
+};
+use serde_json::{Map, Value};
+
+use crate::{entities::SubmissionConfig, interpolate, schema::submission_schema};
+
+static SCHEMA: LazyLock<Value> = LazyLock::new(|| submission_schema().to_value());
+
+/// An error of parsing the submission config, which locates the invalid value
+#[derive(Debug, Clone, Serialize)]
+pub struct SubmissionParseError {
+    /// The path of the invalid value such as `steps.run.parallel[3].files[0]`,
+    /// which is empty for the whole config
+    pub path: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+
+    pub message: String,
+
+    /// Why each variant is rejected if the value matches none of them
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<SubmissionVariantError>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SubmissionVariantError {
+    pub variant: String,
+    pub path: String,
+    pub message: String,
+}
+
+impl Display for SubmissionParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
+        }
+        write!(f, "{}", self.message)?;
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, " at line {line} column {column}")?;
+        }
+
+        for (i, variant) in self.variants.iter().enumerate() {
+            write!(f, "{} {}: ", if i == 0 { ", tried" } else { ";" }, variant.variant)?;
+            if variant.path != self.path {
+                write!(f, "{}: ", variant.path)?;
+            }
+            write!(f, "{}", variant.message)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for SubmissionParseError {}
+
+/// Parses the submission config, a [`SubmissionParseError`] is returned if the
+/// config is not a valid submission
+pub fn parse_submission(config_yaml: &str) -> Result<Arc<SubmissionConfig>> {
+    let mut config = serde_yaml::from_str(config_yaml).map_err(|err| {
+        let location = err.location();
+        let mut message = err.to_string();
+        if let Some(location) = &location {
+            let suffix = format!(" at line {} column {}", location.line(), location.column());
+            message.truncate(message.strip_suffix(&suffix).unwrap_or(&message).len());
+        }
+
+        SubmissionParseError {
+            path: String::new(),
+            line: location.as_ref().map(serde_yaml::Location::line),
+            column: location.as_ref().map(serde_yaml::Location::column),
+            message,
+            variants: vec![],
+        }
+    })?;
+    interpolate::interpolate_submission_vars(&mut config)?;
+
+    match serde_path_to_error::deserialize(&config) {
+        Ok(config) => Ok(Arc::new(config)),
+        Err(err) => Err(diagnose(config_yaml, &config, err).into()),
+    }
+}
+
+/// Locates the error with the schema of the submission since the untagged
+/// enums of the submission format hide the path of the invalid value, the error
+/// reported by serde is used if the config satisfies the schema
+fn diagnose(
+    config_yaml: &str,
+    config: &serde_yaml::Value,
+    err: serde_path_to_error::Error<serde_yaml::Error>,
+) -> SubmissionParseError {
+    let schema_error = serde_json::to_value(config).ok().and_then(|config| {
+        let defs = SCHEMA.get("$defs").and_then(Value::as_object);
+        let mut errors = vec![];
+        SchemaChecker { defs }.check(&SCHEMA, &config, None, &mut vec![], &mut errors);
+        errors.into_iter().next()
+    });
+
+    let (path, message, variants) = match schema_error {
+        Some(error) => (error.path, error.message, error.variants),
+        None => {
+            let path = err
+                .path()
+                .iter()
+                .filter_map(|segment| match segment {
+                    serde_path_to_error::Segment::Map { key } => Some(Segment::Key(key.clone())),
+                    serde_path_to_error::Segment::Seq { index } => Some(Segment::Index(*index)),
+                    _ => None,
+                })
+                .collect();
+            (path, err.into_inner().to_string(), vec![])
+        }
+    };
+
+    let location = Locator(&path)
+        .deserialize(serde_yaml::Deserializer::from_str(config_yaml))
+        .err()
+        .and_then(|err| err.location());
+    SubmissionParseError {
+        path: format_path(&path),
+        line: location.as_ref().map(serde_yaml::Location::line),
+        column: location.as_ref().map(serde_yaml::Location::column),
+        message,
+        variants,
+    }
+}
+
+/// A segment of the path to a value in the submission config
+#[derive(Debug, Clone)]
+pub(crate) enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Formats the path like `steps.run.parallel[3].files[0]`
+pub(crate) fn format_path(path: &[Segment]) -> String {
+    let mut result = String::new();
+    for segment in path {
+        match segment {
+            Segment::Key(key) if result.is_empty() => result.push_str(key),
+            Segment::Key(key) => {
+                result.push('.');
+                result.push_str(key);
+            }
+            Segment::Index(index) => result.push_str(&format!("[{index}]")),
+        }
+    }
+    result
+}
+
+#[derive(Debug)]
+struct SchemaError {
+    path: Vec<Segment>,
+    message: String,
+    variants: Vec<SubmissionVariantError>,
+
+    /// The found value if it differs from the constant telling the variants
+    /// apart, such as the `action` of an action task
+    mismatched_const: Option<String>,
+}
+
+impl SchemaError {
+    fn new(path: &[Segment], message: String) -> Self {
+        Self { path: path.to_vec(), message, variants: vec![], mismatched_const: None }
+    }
+}
+
+/// Checks a value against the subset of JSON Schema generated for the
+/// submission format
+struct SchemaChecker<'a> {
+    defs: Option<&'a Map<String, Value>>,
+}
+
+impl SchemaChecker<'_> {
+    fn check(
+        &self,
+        schema: &Value,
+        value: &Value,
+        name: Option<&str>,
+        path: &mut Vec<Segment>,
+        errors: &mut Vec<SchemaError>,
+    ) {
+        let schema = match schema {
+            Value::Object(schema) => schema,
+            Value::Bool(false) => {
+                return errors.push(SchemaError::new(path, "unexpected value".to_owned()));
+            }
+            _ => return,
+        };
+
+        if let Some(types) = schema.get("type") {
+            let types = match types {
+                Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+                types => types.as_str().into_iter().collect::<Vec<_>>(),
+            };
+            if !types.iter().any(|ty| is_type(ty, value)) {
+                return errors.push(SchemaError::new(
+                    path,
+                    format!(
+                        "invalid type: expected {}, found {}",
+                        types.join(" or "),
+                        type_name(value)
+                    ),
+                ));
+            }
+        }
+
+        if let Some(expected) = schema.get("const") {
+            if expected != value {
+                return errors.push(SchemaError {
+                    mismatched_const: Some(display(value)),
+                    ..SchemaError::new(path, format!("expected `{}`", display(expected)))
+                });
+            }
+        }
+
+        if let Some(Value::Array(expected)) = schema.get("enum") {
+            if !expected.contains(value) {
+                let expected = expected.iter().map(|value| format!("`{}`", display(value)));
+                return errors.push(SchemaError::new(
+                    path,
+                    format!(
+                        "unknown variant `{}`, expected one of {}",
+                        display(value),
+                        expected.collect::<Vec<_>>().join(", ")
+                    ),
+                ));
+            }
+        }
+
+        if let Some(number) = value.as_f64() {
+            let bound = |key| schema.get(key).filter(|bound| bound.is_number());
+            if let Some(minimum) = bound("minimum").filter(|min| Some(number) < min.as_f64()) {
+                errors.push(SchemaError::new(
+                    path,
+                    format!("invalid value: {value}, expected at least {minimum}"),
+                ));
+            }
+            if let Some(maximum) = bound("maximum").filter(|max| Some(number) > max.as_f64()) {
+                errors.push(SchemaError::new(
+                    path,
+                    format!("invalid value: {value}, expected at most {maximum}"),
+                ));
+            }
+        }
+
+        if let Value::Object(object) = value {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (key, property) in properties.into_iter().flatten() {
+                if let Some(value) = object.get(key) {
+                    path.push(Segment::Key(key.clone()));
+                    self.check(property, value, None, path, errors);
+                    path.pop();
+                }
+            }
+
+            for key in schema.get("required").and_then(Value::as_array).into_iter().flatten() {
+                let Some(key) = key.as_str() else { continue };
+                if !object.contains_key(key) {
+                    errors.push(SchemaError::new(path, format!("missing field `{key}`")));
+                }
+            }
+
+            if let Some(additional) = schema.get("additionalProperties") {
+                for (key, value) in object {
+                    if properties.is_some_and(|properties| properties.contains_key(key)) {
+                        continue;
+                    }
+
+                    path.push(Segment::Key(key.clone()));
+                    if additional == &Value::Bool(false) {
+                        errors.push(SchemaError::new(path, format!("unknown field `{key}`")));
+                    } else {
+                        self.check(additional, value, None, path, errors);
+                    }
+                    path.pop();
+                }
+            }
+        }
+
+        if let (Value::Array(items), Some(item)) = (value, schema.get("items")) {
+            for (i, value) in items.iter().enumerate() {
+                path.push(Segment::Index(i));
+                self.check(item, value, None, path, errors);
+                path.pop();
+            }
+        }
+
+        if let Some(name) = schema
+            .get("$ref")
+            .and_then(Value::as_str)
+            .and_then(|reference| reference.strip_prefix("#/$defs/"))
+        {
+            if let Some(def) = self.defs.and_then(|defs| defs.get(name)) {
+                self.check(def, value, Some(name), path, errors);
+            }
+        }
+
+        for key in ["anyOf", "oneOf"] {
+            if let Some(Value::Array(variants)) = schema.get(key) {
+                self.check_variants(variants, value, name, path, errors);
+            }
+        }
+    }
+
+    /// Reports the errors of the only variant which the value is meant to be,
+    /// or why each variant is rejected if it is ambiguous
+    fn check_variants(
+        &self,
+        variants: &[Value],
+        value: &Value,
+        name: Option<&str>,
+        path: &mut Vec<Segment>,
+        errors: &mut Vec<SchemaError>,
+    ) {
+        let mut failures = vec![];
+        for variant in variants {
+            // An optional value is not meant to be null if it is given
+            if !value.is_null() && variant.get("type") == Some(&Value::from("null")) {
+                continue;
+            }
+
+            let mut variant_errors = vec![];
+            self.check(variant, value, None, path, &mut variant_errors);
+            if variant_errors.is_empty() {
+                return;
+            }
+            failures.push((variant, variant_errors));
+        }
+
+        // The variant rejecting only the nested values is the one the value is
+        // meant to be, while the others reject the shape of the value itself
+        let plausible = failures
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, variant_errors))| {
+                variant_errors
+                    .iter()
+                    .all(|error| error.path.len() > path.len() && error.mismatched_const.is_none())
+            })
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        if let [index] = plausible[..] {
+            return errors.extend(failures.swap_remove(index).1);
+        }
+        if failures.len() == 1 {
+            return errors.extend(failures.swap_remove(0).1);
+        }
+
+        let mismatched_consts = failures
+            .iter()
+            .map(|(_, variant_errors)| {
+                variant_errors.iter().find(|error| error.mismatched_const.is_some())
+            })
+            .collect::<Option<Vec<_>>>();
+        if let Some(mismatched_consts) = mismatched_consts {
+            let first = mismatched_consts[0];
+            if mismatched_consts
+                .iter()
+                .all(|error| format_path(&error.path) == format_path(&first.path))
+            {
+                let expected = mismatched_consts
+                    .iter()
+                    .filter_map(|error| error.message.strip_prefix("expected "))
+                    .collect::<Vec<_>>();
+                return errors.push(SchemaError::new(
+                    &first.path,
+                    format!(
+                        "unknown variant `{}`, expected one of {}",
+                        first.mismatched_const.as_deref().unwrap_or_default(),
+                        expected.join(", ")
+                    ),
+                ));
+            }
+        }
+
+        let message = match name {
+            Some(name) => format!("did not match any variant of `{name}`"),
+            None => "did not match any variant".to_owned(),
+        };
+        errors.push(SchemaError {
+            variants: failures
+                .into_iter()
+                .map(|(variant, variant_errors)| {
+                    let error = &variant_errors[0];
+                    SubmissionVariantError {
+                        variant: variant_name(variant),
+                        path: format_path(&error.path),
+                        message: error.message.clone(),
+                    }
+                })
+                .collect(),
+            ..SchemaError::new(path, message)
+        });
+    }
+}
+
+fn is_type(ty: &str, value: &Value) -> bool {
+    match ty {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(number) if number.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn display(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn variant_name(variant: &Value) -> String {
+    if let Some(reference) = variant.get("$ref").and_then(Value::as_str) {
+        return reference.trim_start_matches("#/$defs/").to_owned();
+    }
+
+    let required = variant
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|keys| keys.iter().filter_map(Value::as_str).collect::<Vec<_>>())
+        .unwrap_or_default();
+    if !required.is_empty() {
+        return format!("object with {}", required.join(", "));
+    }
+
+    match variant.get("type") {
+        Some(Value::Array(types)) => {
+            types.iter().filter_map(Value::as_str).collect::<Vec<_>>().join(" or ")
+        }
+        Some(Value::String(ty)) => ty.clone(),
+        _ => "unknown".to_owned(),
+    }
+}
+
+/// Walks the YAML document along the path and fails at the value it leads to,
+/// or at the deepest existing value, so the error is marked with its location
+struct Locator<'a>(&'a [Segment]);
+
+impl<'de> DeserializeSeed<'de> for Locator<'_> {
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Locator<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("the located value")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        if let Some((Segment::Key(expected), rest)) = self.0.split_first() {
+            while let Some(key) = map.next_key::<serde_yaml::Value>()? {
+                let matched = match &key {
+                    serde_yaml::Value::String(key) => key == expected,
+                    serde_yaml::Value::Number(key) => &key.to_string() == expected,
+                    serde_yaml::Value::Bool(key) => &key.to_string() == expected,
+                    _ => false,
+                };
+                if matched {
+                    return map.next_value_seed(Locator(rest));
+                }
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+
+        Err(de::Error::custom("located"))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        if let Some((Segment::Index(expected), rest)) = self.0.split_first() {
+            for _ in 0..*expected {
+                if seq.next_element::<IgnoredAny>()?.is_none() {
+                    return Err(de::Error::custom("located"));
+                }
+            }
+            if seq.next_element_seed(Locator(rest))?.is_some() {
+                return Ok(());
+            }
+        }
+
+        Err(de::Error::custom("located"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SubmissionParseError, parse_submission};
+
+    fn parse_error(config_yaml: &str) -> SubmissionParseError {
+        parse_submission(config_yaml).unwrap_err().downcast().unwrap()
+    }
+
+    #[test]
+    fn test_parse_submission() {
+        let error = parse_error(
+            "steps:
+  prepare:
+    action: seele/noop@1
+  run:
+    parallel:
+      - action: seele/noop@1
+      - action: seele/noop@1
+      - action: seele/noop@1
+      - action: seele/add-file@1
+        files:
+          - path: main.c
+            text: int main() {}
+",
+        );
+        assert_eq!(error.path, "steps.run.parallel[3].files[0]");
+        assert_eq!((error.line, error.column), (Some(11), Some(13)));
+        assert_eq!(error.message, "did not match any variant of `FileItem`");
+        assert_eq!(
+            error.variants.iter().map(|variant| variant.variant.as_str()).collect::<Vec<_>>(),
+            vec!["object with url", "object with plain", "object with base64", "object with local"]
+        );
+        assert_eq!(error.variants[0].message, "missing field `url`");
+
+        let error = parse_error(
+            "steps:
+  run:
+    action: seele/unknown@1
+",
+        );
+        assert_eq!(error.path, "steps.run.action");
+        assert_eq!((error.line, error.column), (Some(3), Some(13)));
+        assert!(error.message.starts_with("unknown variant `seele/unknown@1`, expected one of"));
+
+        let error = parse_error(
+            "steps:
+  run:
+    action: seele/noop@1
+    needs: 3
+",
+        );
+        assert_eq!(error.path, "steps.run.needs");
+        assert_eq!(error.variants.len(), 2);
+
+        let error = parse_error("steps: [");
+        assert_eq!(error.path, "");
+        assert!(error.line.is_some());
+    }
+}
@@ -1,19 +1,248 @@
-use crate::entities::{TaskNode, TaskStatus};
+use std::{cmp::Ordering, sync::Arc};
 
-const TRUE: &str = "true";
-const PREVIOUS_OK: &str = "previous.ok";
+use serde_json::Value;
 
-pub fn check_node_predicate(parent_node: &TaskNode, node: &TaskNode) -> bool {
-    let predicate = match &node.config.when {
-        Some(when) => when.as_str(),
-        None => PREVIOUS_OK,
-    };
+use crate::entities::{
+    Predicate, PredicateField, PredicateOperator, TaskConfig, TaskNode, TaskStatus,
+};
+
+pub fn check_node_predicate(node: &TaskNode) -> bool {
+    is_truthy(&evaluate(&node.when, node))
+}
 
+fn evaluate(predicate: &Predicate, node: &TaskNode) -> Value {
     match predicate {
-        TRUE => true,
-        PREVIOUS_OK => {
-            matches!(*parent_node.config.status.read().unwrap(), TaskStatus::Success { .. })
+        Predicate::Literal(value) => value.clone(),
+        Predicate::Always => Value::Bool(true),
+        Predicate::Previous(field) => read_previous_field(&node.parents, field),
+        Predicate::Step { name, field } => match node.steps.get(name) {
+            None => Value::Null,
+            Some(config) => read_field(config, field),
+        },
+        Predicate::Not(inner) => Value::Bool(!is_truthy(&evaluate(inner, node))),
+        Predicate::And(left, right) => {
+            Value::Bool(is_truthy(&evaluate(left, node)) && is_truthy(&evaluate(right, node)))
+        }
+        Predicate::Or(left, right) => {
+            Value::Bool(is_truthy(&evaluate(left, node)) || is_truthy(&evaluate(right, node)))
+        }
+        Predicate::Compare { left, operator, right } => {
+            Value::Bool(compare(&evaluate(left, node), *operator, &evaluate(right, node)))
+        }
+    }
+}
+
+/// With multiple predecessors, `ok` holds when all of them succeeded while
+/// `failed` and `skipped` hold when any of them did. Value fields are rejected
+/// at resolve time then.
+fn read_previous_field(parents: &[Arc<TaskConfig>], field: &PredicateField) -> Value {
+    match (parents, field) {
+        ([parent], _) => read_field(parent, field),
+        (_, PredicateField::Ok) => {
+            Value::Bool(parents.iter().all(|parent| is_truthy(&read_field(parent, field))))
         }
-        _ => false,
+        (_, PredicateField::Failed | PredicateField::Skipped) => {
+            Value::Bool(parents.iter().any(|parent| is_truthy(&read_field(parent, field))))
+        }
+        _ => Value::Null,
+    }
+}
+
+fn read_field(config: &TaskConfig, field: &PredicateField) -> Value {
+    let status = config.status.read().unwrap();
+    match field {
+        PredicateField::Ok => Value::Bool(matches!(*status, TaskStatus::Success { .. })),
+        PredicateField::Failed => Value::Bool(matches!(*status, TaskStatus::Failed { .. })),
+        PredicateField::Skipped => Value::Bool(matches!(*status, TaskStatus::Skipped)),
+        PredicateField::Status => serde_json::to_value(&*status)
+            .ok()
+            .map(|value| value["status"].clone())
+            .unwrap_or_default(),
+        PredicateField::Tag(key) => match config.tags.as_ref().and_then(|tags| tags.get(key)) {
+            None => Value::Null,
+            Some(value) => Value::String(value.clone()),
+        },
+        PredicateField::Output(key) => {
+            config.output_values.read().unwrap().get(key).cloned().unwrap_or_default()
+        }
+        PredicateField::Report(path) => {
+            let Ok(mut value) = serde_json::to_value(&*status) else {
+                return Value::Null;
+            };
+            for segment in ["report"].into_iter().chain(path.iter().map(String::as_str)) {
+                value = match value {
+                    Value::Object(mut map) => map.remove(segment).unwrap_or_default(),
+                    _ => return Value::Null,
+                };
+            }
+            value
+        }
+    }
+}
+
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(value) => *value,
+        Value::Number(value) => value.as_f64().is_some_and(|value| value != 0.0),
+        Value::String(value) => !value.is_empty(),
+        Value::Array(_) | Value::Object(_) => true,
+    }
+}
+
+fn compare(left: &Value, operator: PredicateOperator, right: &Value) -> bool {
+    let ordering = match (left, right) {
+        (Value::Number(left), Value::Number(right)) => {
+            left.as_f64().zip(right.as_f64()).and_then(|(left, right)| left.partial_cmp(&right))
+        }
+        (Value::String(left), Value::String(right)) => Some(left.cmp(right)),
+        _ => (left == right).then_some(Ordering::Equal),
+    };
+
+    match operator {
+        PredicateOperator::Eq => ordering == Some(Ordering::Equal),
+        PredicateOperator::Ne => ordering != Some(Ordering::Equal),
+        PredicateOperator::Lt => ordering == Some(Ordering::Less),
+        PredicateOperator::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+        PredicateOperator::Gt => ordering == Some(Ordering::Greater),
+        PredicateOperator::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::Utc;
+
+    use crate::{
+        entities::{
+            ActionFailedReport, ActionFailureReportExt, ActionSuccessReport,
+            ActionSuccessReportExt, Submission, TaskFailedReport, TaskStatus, TaskSuccessReport,
+            run_container,
+        },
+        resolve::resolve_submission,
+    };
+
+    fn resolve(yaml: &str) -> Submission {
+        resolve_submission(Arc::new(serde_yaml::from_str(yaml).unwrap()), "test".into()).unwrap()
+    }
+
+    fn container_status(status: run_container::ExecutionStatus, exit_code: i64) -> TaskStatus {
+        let report = run_container::ExecutionReport {
+            status,
+            exit_code,
+            signal: None,
+            wall_time_ms: 0,
+            cpu_user_time_ms: 0,
+            cpu_kernel_time_ms: 0,
+            memory_usage_kib: 0,
+        };
+        match report.status {
+            run_container::ExecutionStatus::Normal => TaskStatus::Success {
+                report: TaskSuccessReport::Action(ActionSuccessReport {
+                    run_at: Utc::now(),
+                    time_elapsed_ms: 0,
+                    previous_attempts: vec![],
+                    ext: ActionSuccessReportExt::RunContainer(report),
+                }),
+            },
+            _ => TaskStatus::Failed {
+                report: TaskFailedReport::Action(ActionFailedReport {
+                    run_at: Utc::now(),
+                    time_elapsed_ms: 0,
+                    previous_attempts: vec![],
+                    ext: ActionFailureReportExt::RunContainer(report),
+                }),
+            },
+        }
+    }
+
+    #[test]
+    fn test_check_node_predicate() {
+        let submission = resolve(
+            r#"
+            steps:
+              compile:
+                action: seele/noop@1
+                tags:
+                  lang: cpp
+              run:
+                action: seele/noop@1
+              on-success:
+                needs: run
+                action: seele/noop@1
+              on-failure:
+                needs: run
+                when: previous.failed
+                action: seele/noop@1
+              on-cpp:
+                needs: run
+                when: steps.compile.tags.lang == "cpp" && steps.compile.ok
+                action: seele/noop@1
+              on-exit-code:
+                needs: run
+                when: always && (previous.report.exit_code > 0 || previous.report.status == 'NORMAL')
+                action: seele/noop@1
+            "#,
+        );
+
+        let compile = submission.root_node.tasks[0].clone();
+        let run = compile.children[0].clone();
+        let check = |name: &str| {
+            let node = run.children.iter().find(|node| node.name == format!(".{name}")).unwrap();
+            super::check_node_predicate(node)
+        };
+
+        *compile.config.status.write().unwrap() =
+            container_status(run_container::ExecutionStatus::Normal, 0);
+        *run.config.status.write().unwrap() =
+            container_status(run_container::ExecutionStatus::Normal, 0);
+        assert!(check("on-success"));
+        assert!(!check("on-failure"));
+        assert!(check("on-cpp"));
+        assert!(check("on-exit-code"));
+
+        *run.config.status.write().unwrap() =
+            container_status(run_container::ExecutionStatus::RuntimeError, 1);
+        assert!(!check("on-success"));
+        assert!(check("on-failure"));
+        assert!(check("on-exit-code"));
+
+        *run.config.status.write().unwrap() =
+            container_status(run_container::ExecutionStatus::SignalTerminate, -1);
+        assert!(!check("on-exit-code"));
+
+        *compile.config.status.write().unwrap() = TaskStatus::Skipped;
+        assert!(!check("on-cpp"));
+    }
+
+    #[test]
+    fn test_resolve_invalid_predicates() {
+        for when in ["previous.unknown", "steps.missing.ok", "steps.second.ok", "previous.ok &&"] {
+            let yaml = format!(
+                "steps:\n  first:\n    action: seele/noop@1\n  second:\n    when: '{when}'\n    \
+                 action: seele/noop@1\n"
+            );
+            let result =
+                resolve_submission(Arc::new(serde_yaml::from_str(&yaml).unwrap()), "test".into());
+            assert!(result.is_err(), "case {when}");
+        }
+
+        let yaml = r#"
+            steps:
+              compile:
+                action: seele/noop@1
+              first:
+                needs: compile
+                action: seele/noop@1
+              second:
+                needs: compile
+                when: steps.first.ok
+                action: seele/noop@1
+        "#;
+        let result =
+            resolve_submission(Arc::new(serde_yaml::from_str(yaml).unwrap()), "test".into());
+        assert!(format!("{:#}", result.unwrap_err()).contains("ancestors"));
     }
 }
@@ -12,8 +12,11 @@ use tokio::{
 };
 use tracing::{info, instrument};
 
-use crate::entities::{
-    SubmissionReportEmbedConfig, SubmissionReportUploadConfig, SubmissionReportUploadMethod,
+use crate::{
+    entities::{
+        SubmissionReportEmbedConfig, SubmissionReportUploadConfig, SubmissionReportUploadMethod,
+    },
+    shared::file,
 };
 
 pub async fn apply_embeds_config(
@@ -22,10 +25,8 @@ pub async fn apply_embeds_config(
 ) -> Result<HashMap<String, String>> {
     Ok(HashMap::from_iter({
         future::try_join_all(embeds.iter().map(|config| async move {
-            // TODO: Should we check for malicious paths?
-            let path = root.join(&config.path);
-
             async {
+                let path = file::resolve_path(root, &config.path)?;
                 let Ok(metadata) = fs::metadata(&path).await else {
                     if !config.ignore_if_missing {
                         bail!("Failed to open the file");
@@ -52,7 +53,7 @@ pub async fn apply_embeds_config(
                 anyhow::Ok(Some((config.field.clone(), content)))
             }
             .await
-            .with_context(|| format!("Error handling the file: {}", path.display()))
+            .with_context(|| format!("Error handling the file: {}", config.path.display()))
         }))
         .await?
         .into_iter()
@@ -68,9 +69,8 @@ pub async fn apply_uploads_config(
     uploads: &[SubmissionReportUploadConfig],
 ) -> Result<()> {
     let results = future::join_all(uploads.iter().map(|config| async move {
-        let path = root.join(&config.path);
-
         async {
+            let path = file::resolve_path(root, &config.path)?;
             let Ok(metadata) = fs::metadata(&path).await else {
                 if !config.ignore_if_missing {
                     bail!("Failed to open the file");
@@ -98,7 +98,7 @@ pub async fn apply_uploads_config(
             Ok(())
         }
         .await
-        .with_context(|| format!("Error uploading the file: {}", path.display()))
+        .with_context(|| format!("Error uploading the file: {}", config.path.display()))
     }))
     .await;
 
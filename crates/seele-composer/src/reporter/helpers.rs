@@ -0,0 +1,90 @@
+use std::{
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use serde_json::Value;
+
+use crate::shared::file;
+
+/// Resolves a path relative to the submission root, `None` if it does not
+/// exist. The path must stay inside the root after following the symbolic
+/// links, so the scripts cannot read the files of the host.
+pub fn resolve_path(root: &Path, path: &str) -> Result<Option<PathBuf>> {
+    let resolved = file::resolve_path(root, Path::new(path))?;
+    Ok(resolved.exists().then_some(resolved))
+}
+
+/// Reads at most `max_bytes` of the file, the invalid UTF-8 sequences are
+/// replaced like the embeds. Only regular files are read, since opening a FIFO
+/// blocks until a writer appears.
+pub fn read_file(root: &Path, path: &str, max_bytes: u64) -> Result<String> {
+    let resolved =
+        resolve_path(root, path)?.with_context(|| format!("The file does not exist: {path}"))?;
+    let metadata =
+        fs::metadata(&resolved).with_context(|| format!("Error reading the metadata: {path}"))?;
+    if !metadata.is_file() {
+        bail!("Not a regular file: {path}");
+    }
+
+    let mut buffer = vec![];
+    File::open(&resolved)
+        .and_then(|file| file.take(max_bytes).read_to_end(&mut buffer))
+        .with_context(|| format!("Error reading the file: {path}"))?;
+    Ok(String::from_utf8_lossy(&buffer).into_owned())
+}
+
+/// Finds the status of a task by the dot-separated names of the steps from the
+/// root, the tasks in a parallel task are named by their indexes, such as
+/// `standard.0.run`
+pub fn find_task<'a>(status: &'a Value, name: &str) -> Option<&'a Value> {
+    name.split('.').try_fold(status, |task, segment| match task.get("steps") {
+        Some(steps) => steps.get(segment),
+        None => task.get("parallel")?.get(segment.parse::<usize>().ok()?),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process::Command};
+
+    use serde_json::json;
+
+    #[test]
+    fn test_resolve_path() {
+        let root = env::temp_dir().join(format!("seele-reporter-{:x}", rand::random::<u64>()));
+        fs::create_dir_all(root.join("nested")).unwrap();
+        fs::write(root.join("nested/out.txt"), "114514").unwrap();
+        std::os::unix::fs::symlink("/etc", root.join("etc")).unwrap();
+
+        assert_eq!(super::read_file(&root, "nested/out.txt", 3).unwrap(), "114");
+        assert!(super::read_file(&root, "nested", 3).is_err());
+        let status = Command::new("mkfifo").arg(root.join("fifo")).status().unwrap();
+        assert!(status.success());
+        assert!(super::read_file(&root, "fifo", 3).is_err());
+        assert_eq!(super::resolve_path(&root, "missing.txt").unwrap(), None);
+        for path in ["/etc/passwd", "../out.txt", "nested/../nested/out.txt", "etc/passwd"] {
+            assert!(super::resolve_path(&root, path).is_err(), "case {path}");
+        }
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_find_task() {
+        let status = json!({
+            "steps": {
+                "compile": { "status": "SUCCESS", "steps": { "run": { "status": "FAILED" } } },
+                "standard": { "parallel": [{ "steps": { "run": { "status": "SKIPPED" } } }] }
+            }
+        });
+
+        assert_eq!(super::find_task(&status, "compile.run"), Some(&json!({ "status": "FAILED" })));
+        assert_eq!(super::find_task(&status, "standard.0.run").unwrap()["status"], "SKIPPED");
+        assert_eq!(super::find_task(&status, "compile").unwrap()["status"], "SUCCESS");
+        assert_eq!(super::find_task(&status, "standard.1.run"), None);
+        assert_eq!(super::find_task(&status, "compile.missing"), None);
+    }
+}
@@ -1,143 +1,288 @@
-use std::collections::HashMap;
+use std::{
+    cell::RefCell,
+    mem,
+    path::PathBuf,
+    rc::Rc,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Instant,
+};
 
-use anyhow::{Context, Result, anyhow, bail};
-use quick_js::JsValue;
+use anyhow::{Context as _, Result, anyhow, bail};
+use rquickjs::{
+    CatchResultExt, CaughtError, Coerced, Context, Ctx, Exception, FromJs, Function, Object,
+    Runtime, Type, function::Rest,
+};
+use serde::de::DeserializeOwned;
 use serde_json::Value;
 use tokio::task::spawn_blocking;
 use tracing::instrument;
 
-use crate::{entities::SubmissionReportConfig, reporter::utils::get_oj_status};
+use super::{ReporterOutput, helpers, scoring::calculate_score, utils::get_oj_status};
+use crate::{
+    conf::ReporterConfig,
+    entities::{ScoringConfig, SubmissionReportConfig},
+};
+
+const MAX_LOGS_SIZE_BYTES: usize = 64 * 1024;
 
 #[instrument(skip_all)]
 pub async fn execute_javascript_reporter(
+    root: PathBuf,
     data: Value,
     source: String,
-) -> Result<SubmissionReportConfig> {
-    spawn_blocking(move || run(data, source)).await?
+    config: ReporterConfig,
+) -> ReporterOutput<SubmissionReportConfig> {
+    spawn_blocking(move || run(root, data, source, config))
+        .await
+        .unwrap_or_else(|err| ReporterOutput { result: Err(err.into()), logs: vec![] })
 }
 
-fn run(data: Value, source: String) -> Result<SubmissionReportConfig> {
-    let context = init_context(data).context("Error initializing the context")?;
-    let source = format!("( function(DATA){{{source}}} )( DATA )");
-    match context.eval(&source).context("Error executing the script")? {
-        JsValue::Object(report) => Ok({
-            serde_json::from_value(
-                QuickJsObject(report).try_into().context("Error converting the returned object")?,
-            )
-            .context("Error deserializing the returned the object")?
-        }),
-        _ => bail!("Unknown return value by the reporter script"),
-    }
+/// The lines logged by `console.log`, the lines exceeding the size limit are
+/// dropped to keep the signals small
+#[derive(Default)]
+struct Logs {
+    lines: Vec<String>,
+    size_bytes: usize,
 }
 
-fn init_context(data: Value) -> Result<quick_js::Context> {
-    let context = quick_js::Context::new()?;
+impl Logs {
+    fn push(&mut self, line: String) {
+        if self.size_bytes > MAX_LOGS_SIZE_BYTES {
+            return;
+        }
 
-    context.set_global(
-        "DATA",
-        convert_to_js_value(data).context("Error converting the data into JsValue")?,
-    )?;
+        self.size_bytes += line.len();
+        if self.size_bytes > MAX_LOGS_SIZE_BYTES {
+            self.lines.push(format!("(Logs exceeding {MAX_LOGS_SIZE_BYTES} bytes are dropped)"));
+        } else {
+            self.lines.push(line);
+        }
+    }
+}
 
-    context.add_callback("getOJStatus", get_oj_status_wrapper)?;
+fn run(
+    root: PathBuf,
+    data: Value,
+    source: String,
+    config: ReporterConfig,
+) -> ReporterOutput<SubmissionReportConfig> {
+    let logs = Rc::new(RefCell::new(Logs::default()));
+    let result = run_script(Helpers { root, data, logs: logs.clone() }, &source, config);
+    let logs = mem::take(&mut logs.borrow_mut().lines);
+    ReporterOutput { result, logs }
+}
 
-    Ok(context)
+/// The states used by the helpers provided to the script
+struct Helpers {
+    root: PathBuf,
+    data: Value,
+    logs: Rc<RefCell<Logs>>,
 }
 
-fn get_oj_status_wrapper(
-    run_report: HashMap<String, JsValue>,
-    compare_report: HashMap<String, JsValue>,
-) -> Result<&'static str> {
-    use crate::entities::run_container::ExecutionReport;
+fn run_script(
+    helpers: Helpers,
+    source: &str,
+    config: ReporterConfig,
+) -> Result<SubmissionReportConfig> {
+    let runtime = Runtime::new().context("Error creating the runtime")?;
+    let memory_limit_bytes = 1024 * 1024 * config.memory_limit_mib;
+    runtime.set_memory_limit(memory_limit_bytes.try_into()?);
+
+    // The engine polls the handler while running the script, and throws an
+    // uncatchable error once the handler returns `true`
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let deadline = Instant::now() + config.timeout();
+    runtime.set_interrupt_handler(Some(Box::new({
+        let interrupted = interrupted.clone();
+        move || {
+            if Instant::now() < deadline {
+                return false;
+            }
+            interrupted.store(true, Ordering::Relaxed);
+            true
+        }
+    })));
 
-    let run_report: ExecutionReport =
-        serde_json::from_value(QuickJsObject(run_report).try_into()?)?;
-    let compare_report: ExecutionReport =
-        serde_json::from_value(QuickJsObject(compare_report).try_into()?)?;
+    let context = Context::full(&runtime).context("Error creating the context")?;
+    context.with(|ctx| {
+        let report =
+            evaluate(&ctx, helpers, source, memory_limit_bytes).catch(&ctx).map_err(|err| {
+                if interrupted.load(Ordering::Relaxed) {
+                    anyhow!(
+                        "The reporter script exceeded the time limit of {} ms",
+                        config.timeout_ms
+                    )
+                } else if is_out_of_memory(&err) {
+                    anyhow!(
+                        "The reporter script exceeded the memory limit of {} MiB",
+                        config.memory_limit_mib
+                    )
+                } else {
+                    anyhow!("Error executing the script: {err}")
+                }
+            })?;
 
-    Ok(get_oj_status(run_report, compare_report).into())
+        if report.type_of() != Type::Object {
+            bail!("Unknown return value by the reporter script");
+        }
+        from_js(&ctx, report).context("Error deserializing the returned object")
+    })
 }
 
-struct QuickJsObject(HashMap<String, JsValue>);
+fn evaluate<'js>(
+    ctx: &Ctx<'js>,
+    helpers: Helpers,
+    source: &str,
+    memory_limit_bytes: u64,
+) -> rquickjs::Result<rquickjs::Value<'js>> {
+    let Helpers { root, data, logs } = helpers;
+    let globals = ctx.globals();
+    globals.set("DATA", ctx.json_parse(data.to_string())?)?;
+    globals.set("getOJStatus", Function::new(ctx.clone(), get_oj_status_wrapper)?)?;
+    globals.set("calculateScore", Function::new(ctx.clone(), calculate_score_wrapper)?)?;
 
-impl TryInto<Value> for QuickJsObject {
-    type Error = anyhow::Error;
+    globals.set(
+        "task",
+        Function::new(ctx.clone(), move |ctx: Ctx<'js>, name: String| {
+            match helpers::find_task(&data, &name) {
+                Some(task) => ctx.json_parse(task.to_string()),
+                None => Ok(rquickjs::Value::new_undefined(ctx)),
+            }
+        })?,
+    )?;
 
-    fn try_into(self) -> Result<Value> {
-        convert_to_value(JsValue::Object(self.0))
-    }
+    globals.set(
+        "readFile",
+        Function::new(ctx.clone(), {
+            let root = root.clone();
+            move |ctx: Ctx<'js>, path: String, max_bytes: u64| {
+                helpers::read_file(&root, &path, max_bytes).map_err(|err| throw(&ctx, err))
+            }
+        })?,
+    )?;
+    globals.set(
+        "exists",
+        Function::new(ctx.clone(), {
+            let root = root.clone();
+            move |ctx: Ctx<'js>, path: String| {
+                helpers::resolve_path(&root, &path)
+                    .map(|resolved| resolved.is_some())
+                    .map_err(|err| throw(&ctx, err))
+            }
+        })?,
+    )?;
+    // A file larger than the heap cannot be parsed anyway
+    globals.set(
+        "readJSON",
+        Function::new(ctx.clone(), move |ctx: Ctx<'js>, path: String| {
+            let json = helpers::read_file(&root, &path, memory_limit_bytes)
+                .map_err(|err| throw(&ctx, err))?;
+            ctx.json_parse(json)
+        })?,
+    )?;
+
+    let console = Object::new(ctx.clone())?;
+    console.set(
+        "log",
+        Function::new(ctx.clone(), move |ctx: Ctx<'js>, values: Rest<rquickjs::Value<'js>>| {
+            let line = values
+                .0
+                .into_iter()
+                .map(|value| format_value(&ctx, value))
+                .collect::<rquickjs::Result<Vec<_>>>()?
+                .join(" ");
+            logs.borrow_mut().push(line);
+            rquickjs::Result::Ok(())
+        })?,
+    )?;
+    globals.set("console", console)?;
+
+    ctx.eval(format!("( function(DATA){{{source}}} )( DATA )"))
 }
 
-fn convert_to_value(value: JsValue) -> Result<Value> {
-    use serde_json::Number;
+fn throw(ctx: &Ctx, err: anyhow::Error) -> rquickjs::Error {
+    Exception::throw_message(ctx, &format!("{err:#}"))
+}
 
-    Ok(match value {
-        JsValue::Undefined => Value::Null,
-        JsValue::Null => Value::Null,
-        JsValue::Bool(value) => Value::Bool(value),
-        JsValue::Int(value) => Value::Number(Number::from(value)),
-        JsValue::Float(value) => {
-            Value::Number(Number::from_f64(value).ok_or_else(|| anyhow!("Invalid float number"))?)
-        }
-        JsValue::String(value) => Value::String(value),
-        JsValue::Array(values) => {
-            Value::Array(values.into_iter().map(convert_to_value).collect::<Result<_>>()?)
+/// Formats the values like `console.log` in the browsers, the objects are
+/// formatted as JSON
+fn format_value<'js>(ctx: &Ctx<'js>, value: rquickjs::Value<'js>) -> rquickjs::Result<String> {
+    if let Some(string) = value.as_string() {
+        return string.to_string();
+    }
+
+    if value.is_object() && !value.is_function() {
+        if let Some(json) = ctx.json_stringify(value.clone())? {
+            return json.to_string();
         }
-        JsValue::Object(values) => Value::Object(
-            values
-                .into_iter()
-                .map(|(key, value)| convert_to_value(value).map(|value| (key, value)))
-                .collect::<Result<_>>()?,
-        ),
-        _ => bail!("Unknown value detected"),
-    })
+    }
+
+    Ok(Coerced::<String>::from_js(ctx, value)?.0)
 }
 
-fn convert_to_js_value(value: Value) -> Result<JsValue> {
-    Ok(match value {
-        Value::Null => JsValue::Null,
-        Value::Bool(value) => JsValue::Bool(value),
-        Value::String(value) => JsValue::String(value),
-        Value::Number(value) => {
-            if value.is_f64() {
-                JsValue::Float(
-                    value
-                        .as_f64()
-                        .ok_or_else(|| anyhow!("Error getting the value as f64: {value}"))?,
-                )
-            } else if value.is_i64() {
-                JsValue::Int(
-                    value
-                        .as_i64()
-                        .ok_or_else(|| anyhow!("Error getting the value as i64: {value}"))?
-                        .try_into()
-                        .with_context(|| format!("Error converting i64 into i32: {value}"))?,
-                )
-            } else {
-                JsValue::Int(
-                    value
-                        .as_u64()
-                        .ok_or_else(|| anyhow!("Error getting the value as u64: {value}"))?
-                        .try_into()
-                        .with_context(|| format!("Error converting u64 into i32: {value}"))?,
-                )
-            }
+fn is_out_of_memory(err: &CaughtError) -> bool {
+    match err {
+        CaughtError::Error(rquickjs::Error::Allocation) => true,
+        CaughtError::Exception(exception) => {
+            exception.message().is_some_and(|message| message == "out of memory")
         }
-        Value::Array(values) => {
-            JsValue::Array(values.into_iter().map(convert_to_js_value).collect::<Result<_>>()?)
-        }
-        Value::Object(values) => JsValue::Object(
-            values
-                .into_iter()
-                .map(|(key, value)| convert_to_js_value(value).map(|value| (key, value)))
-                .collect::<Result<_>>()?,
-        ),
-    })
+        _ => false,
+    }
+}
+
+fn get_oj_status_wrapper<'js>(
+    ctx: Ctx<'js>,
+    run_report: rquickjs::Value<'js>,
+    compare_report: rquickjs::Value<'js>,
+) -> rquickjs::Result<&'static str> {
+    use crate::entities::run_container::ExecutionReport;
+
+    let convert = |report| {
+        from_js::<ExecutionReport>(&ctx, report)
+            .map_err(|err| Exception::throw_type(&ctx, &format!("{err:#}")))
+    };
+
+    Ok(get_oj_status(convert(run_report)?, convert(compare_report)?).into())
+}
+
+fn calculate_score_wrapper<'js>(
+    ctx: Ctx<'js>,
+    config: rquickjs::Value<'js>,
+    results: rquickjs::Value<'js>,
+) -> rquickjs::Result<rquickjs::Value<'js>> {
+    let result = (|| {
+        let config = from_js::<ScoringConfig>(&ctx, config).context("Invalid scoring config")?;
+        let results = from_js::<Vec<f64>>(&ctx, results).context("Invalid results of the cases")?;
+        anyhow::Ok(serde_json::to_string(&calculate_score(&config, &results)?)?)
+    })()
+    .map_err(|err| throw(&ctx, err))?;
+    ctx.json_parse(result)
+}
+
+/// Converts the value through JSON, so it is converted the same way as
+/// `JSON.stringify` in the script
+fn from_js<'js, T: DeserializeOwned>(ctx: &Ctx<'js>, value: rquickjs::Value<'js>) -> Result<T> {
+    let json = ctx
+        .json_stringify(value)
+        .context("Error serializing the value")?
+        .context("The value is not serializable")?
+        .to_string()?;
+    Ok(serde_json::from_str(&json)?)
 }
 
 #[cfg(test)]
 mod tests {
-    use map_macro::hash_map;
-    use quick_js::JsValue;
+    use std::{env, fs};
+
+    use serde_json::json;
+
+    use crate::conf::ReporterConfig;
+
+    const CONFIG: ReporterConfig =
+        ReporterConfig { timeout_ms: 1000, memory_limit_mib: 16, progress_interval_ms: 0 };
 
     #[tokio::test]
     async fn test_execute_javascript_reporter() {
@@ -160,8 +305,9 @@ mod tests {
                       1,4,5,1,4]}}"
             .to_string();
 
-        let report = super::execute_javascript_reporter(data, source).await;
-        let report = report.unwrap().report;
+        let report =
+            super::execute_javascript_reporter(env::temp_dir(), data, source, CONFIG).await;
+        let report = report.result.unwrap().report;
 
         assert_eq!(report["str"], "foo");
         assert_eq!(report["num"], 114);
@@ -174,40 +320,110 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_convert_to_js_value() {
-        let data = serde_json::from_str(
-            r#"{
-                "null": null,
-                "bool": true,
-                "string": "string",
-                "integer": 114514,
-                "float": 114.514,
-                "array": [
-                    "seele",
-                    1,
-                    true
-                ],
-                "object": {
-                    "foo": "114",
-                    "bar": 514
-                }
-            }"#,
-        )
-        .unwrap();
+    async fn test_convert_data() {
+        let data = json!({
+            "null": null,
+            "bool": true,
+            "string": "string",
+            "integer": 114514,
+            "large_integer": 1145141919810u64,
+            "float": 114.514,
+            "array": ["seele", 1, true],
+            "object": { "foo": "114", "bar": 514 }
+        });
+        let source = "return {report:{types:Object.fromEntries(Object.entries(DATA).map(([key, \
+                      value]) => [key, Array.isArray(value) ? 'array' : value === null ? 'null' : \
+                      typeof value])), data:DATA}}"
+            .to_string();
+
+        let report =
+            super::execute_javascript_reporter(env::temp_dir(), data.clone(), source, CONFIG)
+                .await
+                .result
+                .unwrap()
+                .report;
         assert_eq!(
-            super::convert_to_js_value(data).unwrap(),
-            JsValue::Object(hash_map! {
-                "null".to_owned() => JsValue::Null,
-                "bool".to_owned() => JsValue::Bool(true),
-                "string".to_owned() => JsValue::String("string".to_owned()),
-                "integer".to_owned() => JsValue::Int(114514),
-                "float".to_owned() => JsValue::Float(114.514),
-                "array".to_owned() => JsValue::Array(vec![JsValue::String("seele".to_owned()), JsValue::Int(1), JsValue::Bool(true)]),
-                "object".to_owned() => JsValue::Object(hash_map! {
-                    "foo".to_owned() => JsValue::String("114".to_owned()),
-                    "bar".to_owned() => JsValue::Int(514),
-                })
+            serde_json::to_value(&report["types"]).unwrap(),
+            json!({
+                "null": "null",
+                "bool": "boolean",
+                "string": "string",
+                "integer": "number",
+                "large_integer": "number",
+                "float": "number",
+                "array": "array",
+                "object": "object"
             })
+        );
+        assert_eq!(serde_json::to_value(&report["data"]).unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_reporter_limits() {
+        let err = super::execute_javascript_reporter(
+            env::temp_dir(),
+            json!({}),
+            "try { while (true) {} } catch {} return {}".to_string(),
+            ReporterConfig { timeout_ms: 100, ..CONFIG },
+        )
+        .await
+        .result
+        .unwrap_err();
+        assert_eq!(err.to_string(), "The reporter script exceeded the time limit of 100 ms");
+
+        let err = super::execute_javascript_reporter(
+            env::temp_dir(),
+            json!({}),
+            "const arrays = []; while (true) arrays.push(new Array(1e6).fill(1)); return {}"
+                .to_string(),
+            CONFIG,
+        )
+        .await
+        .result
+        .unwrap_err();
+        assert_eq!(err.to_string(), "The reporter script exceeded the memory limit of 16 MiB");
+    }
+
+    #[tokio::test]
+    async fn test_helpers() {
+        let root = env::temp_dir().join(format!("seele-reporter-{:x}", rand::random::<u64>()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("out.txt"), "114514").unwrap();
+        fs::write(root.join("score.json"), r#"{"score": 80}"#).unwrap();
+
+        let data =
+            json!({ "steps": { "compile": { "steps": { "run": { "status": "FAILED" } } } } });
+        let source = "console.log('status', task('compile.run').status, { missing: \
+                      task('compile.missing') === undefined }); return \
+                      {report:{out:readFile('out.txt', \
+                      3),score:readJSON('score.json').score,exists:[exists('out.txt'),exists('\
+                      missing.txt')]}}"
+            .to_string();
+
+        let output = super::execute_javascript_reporter(root.clone(), data, source, CONFIG).await;
+        assert_eq!(output.logs, vec![r#"status FAILED {"missing":true}"#]);
+        let report = serde_json::to_value(output.result.unwrap().report).unwrap();
+        assert_eq!(report, json!({ "out": "114", "score": 80, "exists": [true, false] }));
+
+        let source = "return { report: calculateScore({ subtasks: [{ score: 40, cases: [0, 1] }, \
+                      { score: 60, cases: [1] }] }, [0.5, 1]) }"
+            .to_string();
+        let output =
+            super::execute_javascript_reporter(root.clone(), json!({}), source, CONFIG).await;
+        let report = serde_json::to_value(output.result.unwrap().report).unwrap();
+        assert_eq!(report["score"], 80.0);
+
+        let output = super::execute_javascript_reporter(
+            root.clone(),
+            json!({}),
+            "console.log(1); return { report: { passwd: readFile('../../etc/passwd', 1024) } }"
+                .to_string(),
+            CONFIG,
         )
+        .await;
+        assert_eq!(output.logs, vec!["1"]);
+        assert!(output.result.unwrap_err().to_string().contains("without `..`"));
+
+        fs::remove_dir_all(&root).unwrap();
     }
 }
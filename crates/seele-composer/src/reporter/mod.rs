@@ -5,23 +5,63 @@ use serde_json::Value;
 use tracing::instrument;
 
 use super::report::apply_embeds_config;
-use crate::entities::{SubmissionReportUploadConfig, SubmissionReporter};
+use crate::{
+    conf,
+    entities::{SubmissionReportConfig, SubmissionReportUploadConfig, SubmissionReporter},
+};
 
+mod helpers;
 mod javascript;
+mod oj;
+mod progress;
+mod scoring;
 mod utils;
 
+/// The result of a reporter, with the lines it logged for debugging, which are
+/// kept even if the reporter fails
+pub struct ReporterOutput<T> {
+    pub result: Result<T>,
+    pub logs: Vec<String>,
+}
+
 #[instrument(skip_all)]
 pub async fn execute_reporter(
     root_directory: &Path,
     reporter: &SubmissionReporter,
     data: Value,
-) -> Result<(Value, Vec<SubmissionReportUploadConfig>)> {
-    let mut config = match reporter {
+) -> ReporterOutput<(Value, Vec<SubmissionReportUploadConfig>)> {
+    match reporter {
         SubmissionReporter::JavaScript { javascript } => {
-            javascript::execute_javascript_reporter(data, javascript.to_string()).await?
+            let ReporterOutput { result, logs } = javascript::execute_javascript_reporter(
+                root_directory.to_path_buf(),
+                data,
+                javascript.to_string(),
+                conf::CONFIG.composer.reporter,
+            )
+            .await;
+
+            let result = match result {
+                Ok(config) => apply_reporter_config(root_directory, config).await,
+                Err(err) => Err(err),
+            };
+            ReporterOutput { result, logs }
         }
-    };
+        SubmissionReporter::Oj { oj } => ReporterOutput {
+            result: oj::execute_oj_reporter(&data, oj).map(|report| (report, vec![])),
+            logs: vec![],
+        },
+        SubmissionReporter::Progress { progress } => ReporterOutput {
+            result: progress::execute_progress_reporter(&data, progress)
+                .map(|report| (report, vec![])),
+            logs: vec![],
+        },
+    }
+}
 
+async fn apply_reporter_config(
+    root_directory: &Path,
+    mut config: SubmissionReportConfig,
+) -> Result<(Value, Vec<SubmissionReportUploadConfig>)> {
     let embeds = apply_embeds_config(root_directory, &config.embeds)
         .await
         .context("Error applying the embeds config")?;
@@ -0,0 +1,295 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+
+use super::{
+    helpers::find_task,
+    scoring::{SubtaskScore, calculate_score},
+    utils::{OjStatus, get_oj_status, get_run_oj_status},
+};
+use crate::entities::{
+    OjReporterConfig,
+    run_container::{ExecutionReport, ExecutionStatus},
+};
+
+#[derive(Debug, Serialize)]
+struct OjReport {
+    /// `None` until all the cases have been judged, see `get_verdict`
+    verdict: Option<&'static str>,
+    score: f64,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    subtasks: Vec<SubtaskScore>,
+
+    /// The status of the compilation task
+    #[serde(skip_serializing_if = "Option::is_none")]
+    compile: Option<String>,
+
+    cases: Vec<OjCaseReport>,
+}
+
+#[derive(Debug, Serialize)]
+struct OjCaseReport {
+    /// `None` until the case has been judged
+    verdict: Option<&'static str>,
+    time_ms: Option<u64>,
+    memory_kib: Option<u64>,
+
+    /// Only when the cases are scored individually
+    #[serde(skip_serializing_if = "Option::is_none")]
+    score: Option<f64>,
+}
+
+/// Builds the report from the status natively, so it is cheap enough to run
+/// on every progress signal
+pub fn execute_oj_reporter(data: &Value, config: &OjReporterConfig) -> Result<Value> {
+    let cases = find_task(data, &config.cases)
+        .and_then(|task| task.get("parallel"))
+        .and_then(Value::as_array)
+        .with_context(|| {
+            format!("The parallel task of the cases does not exist: {}", config.cases)
+        })?;
+
+    let cases = cases
+        .iter()
+        .map(|case| {
+            let (verdict, run) = get_case_verdict(case, &config.run, &config.compare);
+            let compare = find_task(case, &config.compare);
+
+            let points = config.points.as_ref().and_then(|name| {
+                compare?.get("outputs")?.get(name)?.as_f64().filter(|_| verdict.is_some())
+            });
+            let result = match (points, verdict) {
+                (Some(points), _) => points,
+                (None, Some(OjStatus::Accepted)) => 1.0,
+                _ => 0.0,
+            };
+
+            let report = OjCaseReport {
+                verdict: verdict.map(Into::into),
+                time_ms: run.as_ref().map(|run| run.cpu_user_time_ms),
+                memory_kib: run.as_ref().map(|run| run.memory_usage_kib),
+                score: None,
+            };
+            (report, result, verdict)
+        })
+        .collect::<Vec<_>>();
+
+    let results = cases.iter().map(|(_, result, _)| *result).collect::<Vec<_>>();
+    let verdicts = cases.iter().map(|(_, _, verdict)| *verdict).collect::<Vec<_>>();
+    let scoring = calculate_score(&config.scoring, &results)?;
+    let mut cases = cases.into_iter().map(|(report, ..)| report).collect::<Vec<_>>();
+    if let Some(scores) = &scoring.cases {
+        for (case, &score) in cases.iter_mut().zip(scores) {
+            case.score = Some(score);
+        }
+    }
+
+    let compile = config.compile.as_ref().map(|name| {
+        find_task(data, name)
+            .and_then(|task| task.get("status"))
+            .and_then(Value::as_str)
+            .unwrap_or("PENDING")
+            .to_owned()
+    });
+    let report = OjReport {
+        verdict: get_verdict(compile.as_deref(), &verdicts).map(Into::into),
+        score: scoring.score,
+        subtasks: scoring.subtasks,
+        compile,
+        cases,
+    };
+    serde_json::to_value(report).context("Error serializing the report")
+}
+
+/// Gets the verdict of the submission. A failed checker fails the judgement
+/// regardless of the other cases, since the results cannot be trusted.
+/// Otherwise, it is the verdict of the first case neither accepted nor
+/// skipped once all the cases have been judged.
+fn get_verdict(compile: Option<&str>, verdicts: &[Option<OjStatus>]) -> Option<OjStatus> {
+    if verdicts.contains(&Some(OjStatus::JudgementFailed)) {
+        return Some(OjStatus::JudgementFailed);
+    }
+    if compile == Some("FAILED") {
+        return Some(OjStatus::CompileError);
+    }
+
+    let verdicts = verdicts.iter().copied().collect::<Option<Vec<_>>>()?;
+    get_judged_verdict(&verdicts)
+}
+
+/// Gets the verdict of the first judged case neither accepted nor skipped
+pub fn get_judged_verdict(verdicts: &[OjStatus]) -> Option<OjStatus> {
+    verdicts
+        .iter()
+        .copied()
+        .find(|verdict| !matches!(verdict, OjStatus::Accepted | OjStatus::Skipped))
+        .or_else(|| verdicts.contains(&OjStatus::Skipped).then_some(OjStatus::Skipped))
+        .or_else(|| (!verdicts.is_empty()).then_some(OjStatus::Accepted))
+}
+
+/// Gets the verdict of a case by the names of its tasks, along with the report
+/// of running the program
+pub fn get_case_verdict(
+    case: &Value,
+    run: &str,
+    compare: &str,
+) -> (Option<OjStatus>, Option<ExecutionReport>) {
+    let run_task = find_task(case, run);
+    let run = run_task.and_then(execution_report);
+    let verdict = match (&run, find_task(case, compare).and_then(execution_report)) {
+        (Some(run), Some(compare)) => Some(get_oj_status(run.clone(), compare)),
+        (Some(run), None) if run.status != ExecutionStatus::Normal => Some(get_run_oj_status(run)),
+        (None, _) if [Some(case), run_task].into_iter().flatten().any(is_skipped) => {
+            Some(OjStatus::Skipped)
+        }
+        _ => None,
+    };
+    (verdict, run)
+}
+
+#[inline]
+fn is_skipped(task: &Value) -> bool {
+    task.get("status").and_then(Value::as_str) == Some("SKIPPED")
+}
+
+/// The report of the container run by the task, `None` if the task has not
+/// run yet
+fn execution_report(task: &Value) -> Option<ExecutionReport> {
+    serde_json::from_value(task.get("report")?.clone()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+
+    use crate::entities::OjReporterConfig;
+
+    fn case(run: &str, compare: Option<&str>) -> Value {
+        let report = |status: &str| {
+            json!({
+                "status": if status == "NORMAL" { "SUCCESS" } else { "FAILED" },
+                "report": {
+                    "type": "run_container",
+                    "status": status,
+                    "exit_code": 0,
+                    "wall_time_ms": 12,
+                    "cpu_user_time_ms": 10,
+                    "cpu_kernel_time_ms": 1,
+                    "memory_usage_kib": 1024
+                }
+            })
+        };
+
+        json!({
+            "status": "SUCCESS",
+            "steps": {
+                "run": report(run),
+                "compare": compare.map_or(json!({ "status": "SKIPPED" }), report)
+            }
+        })
+    }
+
+    #[test]
+    fn test_execute_oj_reporter() {
+        let config: OjReporterConfig = serde_yaml::from_str(
+            "{ compile: compile, cases: judge, scoring: { total: 60, weights: [1, 2, 3] } }",
+        )
+        .unwrap();
+        let data = json!({
+            "steps": {
+                "compile": { "status": "SUCCESS" },
+                "judge": {
+                    "parallel": [
+                        case("NORMAL", Some("NORMAL")),
+                        case("NORMAL", Some("RUNTIME_ERROR")),
+                        case("USER_TIME_LIMIT_EXCEEDED", None),
+                    ]
+                }
+            }
+        });
+
+        let report = super::execute_oj_reporter(&data, &config).unwrap();
+        assert_eq!(report["verdict"], "WA");
+        assert_eq!(report["score"], 10.0);
+        assert_eq!(report["compile"], "SUCCESS");
+        assert_eq!(
+            report["cases"],
+            json!([
+                { "verdict": "ACC", "time_ms": 10, "memory_kib": 1024, "score": 10.0 },
+                { "verdict": "WA", "time_ms": 10, "memory_kib": 1024, "score": 0.0 },
+                { "verdict": "TLE", "time_ms": 10, "memory_kib": 1024, "score": 0.0 },
+            ])
+        );
+
+        let data = json!({
+            "steps": {
+                "judge": {
+                    "parallel": [
+                        case("NORMAL", Some("NORMAL")),
+                        { "status": "RUNNING", "steps": { "run": { "status": "RUNNING" } } },
+                    ]
+                }
+            }
+        });
+        let config: OjReporterConfig = serde_yaml::from_str("{ cases: judge }").unwrap();
+        let report = super::execute_oj_reporter(&data, &config).unwrap();
+        assert_eq!(report["verdict"], Value::Null);
+        assert_eq!(report["score"], 50.0);
+        assert_eq!(report["cases"][1]["verdict"], Value::Null);
+
+        let mut data = json!({
+            "steps": {
+                "judge": {
+                    "parallel": [
+                        case("NORMAL", Some("NORMAL")),
+                        case("NORMAL", Some("RUNTIME_ERROR")),
+                        case("NORMAL", Some("RUNTIME_ERROR")),
+                    ]
+                }
+            }
+        });
+        data["steps"]["judge"]["parallel"][2]["steps"]["compare"]["outputs"] =
+            json!({ "points": 0.5 });
+        let config: OjReporterConfig = serde_yaml::from_str(
+            "{ cases: judge, points: points, scoring: { subtasks: [{ score: 20, cases: [0] }, { \
+             score: 80, cases: [1, 2], rule: sum }] } }",
+        )
+        .unwrap();
+        let report = super::execute_oj_reporter(&data, &config).unwrap();
+        assert_eq!(report["score"], 40.0);
+        assert_eq!(report["subtasks"][1], json!({ "score": 20.0, "full_score": 80.0 }));
+        assert_eq!(
+            report["cases"][2],
+            json!({ "verdict": "WA", "time_ms": 10, "memory_kib": 1024 })
+        );
+
+        let data = json!({
+            "steps": {
+                "compile": { "status": "FAILED" },
+                "judge": {
+                    "status": "SKIPPED",
+                    "parallel": [{ "status": "SKIPPED", "steps": { "run": { "status": "SKIPPED" } } }]
+                }
+            }
+        });
+        let config: OjReporterConfig =
+            serde_yaml::from_str("{ compile: compile, cases: judge }").unwrap();
+        let report = super::execute_oj_reporter(&data, &config).unwrap();
+        assert_eq!(report["verdict"], "CE");
+        assert_eq!(report["cases"][0]["verdict"], "SKIPPED");
+
+        // The verdict does not wait for the cases once the compilation fails
+        let data = json!({
+            "steps": {
+                "compile": { "status": "FAILED" },
+                "judge": { "status": "PENDING", "parallel": [{ "status": "PENDING" }] }
+            }
+        });
+        let report = super::execute_oj_reporter(&data, &config).unwrap();
+        assert_eq!(report["verdict"], "CE");
+
+        let config: OjReporterConfig = serde_yaml::from_str("{ cases: missing }").unwrap();
+        assert!(super::execute_oj_reporter(&data, &config).is_err());
+    }
+}
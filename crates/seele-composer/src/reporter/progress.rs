@@ -0,0 +1,138 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+
+use super::{
+    helpers::find_task,
+    oj::{get_case_verdict, get_judged_verdict},
+};
+use crate::entities::ProgressReporterConfig;
+
+#[derive(Debug, Serialize)]
+struct ProgressReport {
+    completed: usize,
+    total: usize,
+
+    /// The verdict of the judged cases so far, only with `cases`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    verdict: Option<&'static str>,
+}
+
+/// Builds the report from the serialized status natively by counting the
+/// tasks, which saves running a script on every progress tick. The status is
+/// serialized anyway since the progress signals carry it.
+pub fn execute_progress_reporter(data: &Value, config: &ProgressReporterConfig) -> Result<Value> {
+    let report = match &config.cases {
+        None => {
+            let (completed, total) = count_actions(data);
+            ProgressReport { completed, total, verdict: None }
+        }
+        Some(name) => {
+            let task = find_task(data, name)
+                .with_context(|| format!("The task of the cases does not exist: {name}"))?;
+            let cases = subtasks(task)
+                .with_context(|| format!("The task of the cases has no subtasks: {name}"))?;
+
+            let verdicts = cases
+                .iter()
+                .filter_map(|case| get_case_verdict(case, &config.run, &config.compare).0)
+                .collect::<Vec<_>>();
+            ProgressReport {
+                completed: cases.iter().filter(|case| is_completed(case)).count(),
+                total: cases.len(),
+                verdict: get_judged_verdict(&verdicts).map(Into::into),
+            }
+        }
+    };
+    serde_json::to_value(report).context("Error serializing the report")
+}
+
+/// Counts the completed action tasks and all the action tasks under the task
+fn count_actions(task: &Value) -> (usize, usize) {
+    match subtasks(task) {
+        None => (is_completed(task) as usize, 1),
+        Some(tasks) => tasks
+            .into_iter()
+            .map(count_actions)
+            .fold((0, 0), |(completed, total), count| (completed + count.0, total + count.1)),
+    }
+}
+
+fn subtasks(task: &Value) -> Option<Vec<&Value>> {
+    match (task.get("steps"), task.get("parallel")) {
+        (Some(Value::Object(steps)), _) => Some(steps.values().collect()),
+        (_, Some(Value::Array(tasks))) => Some(tasks.iter().collect()),
+        _ => None,
+    }
+}
+
+#[inline]
+fn is_completed(task: &Value) -> bool {
+    !matches!(task.get("status").and_then(Value::as_str), None | Some("PENDING" | "RUNNING"))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::entities::ProgressReporterConfig;
+
+    #[test]
+    fn test_execute_progress_reporter() {
+        let report = |status: &str, exit_code: i64| {
+            json!({
+                "status": "SUCCESS",
+                "report": {
+                    "type": "run_container",
+                    "status": status,
+                    "exit_code": exit_code,
+                    "wall_time_ms": 12,
+                    "cpu_user_time_ms": 10,
+                    "cpu_kernel_time_ms": 1,
+                    "memory_usage_kib": 1024
+                }
+            })
+        };
+        let data = json!({
+            "status": "RUNNING",
+            "steps": {
+                "compile": { "status": "SUCCESS" },
+                "judge": {
+                    "status": "RUNNING",
+                    "parallel": [
+                        {
+                            "status": "SUCCESS",
+                            "steps": { "run": report("NORMAL", 0), "compare": report("NORMAL", 0) }
+                        },
+                        {
+                            "status": "FAILED",
+                            "steps": {
+                                "run": report("NORMAL", 0),
+                                "compare": report("RUNTIME_ERROR", 1)
+                            }
+                        },
+                        {
+                            "status": "RUNNING",
+                            "steps": { "run": { "status": "RUNNING" }, "compare": { "status": "PENDING" } }
+                        },
+                    ]
+                }
+            }
+        });
+
+        let config: ProgressReporterConfig = serde_yaml::from_str("{}").unwrap();
+        let report = super::execute_progress_reporter(&data, &config).unwrap();
+        assert_eq!(report, json!({ "completed": 5, "total": 7 }));
+
+        let config: ProgressReporterConfig = serde_yaml::from_str("{ cases: judge }").unwrap();
+        let report = super::execute_progress_reporter(&data, &config).unwrap();
+        assert_eq!(report, json!({ "completed": 2, "total": 3, "verdict": "WA" }));
+
+        let data = json!({ "steps": { "judge": { "parallel": [{ "status": "PENDING" }] } } });
+        let report = super::execute_progress_reporter(&data, &config).unwrap();
+        assert_eq!(report, json!({ "completed": 0, "total": 1 }));
+
+        let config: ProgressReporterConfig = serde_yaml::from_str("{ cases: missing }").unwrap();
+        assert!(super::execute_progress_reporter(&data, &config).is_err());
+    }
+}
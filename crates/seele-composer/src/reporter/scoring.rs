@@ -0,0 +1,152 @@
+use anyhow::{Result, bail};
+use serde::Serialize;
+
+use crate::entities::{ScoringConfig, SubtaskRule};
+
+#[derive(Debug, Serialize)]
+pub struct ScoringResult {
+    pub score: f64,
+
+    /// The score of each case, only when the cases are scored individually
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cases: Option<Vec<f64>>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub subtasks: Vec<SubtaskScore>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubtaskScore {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub score: f64,
+    pub full_score: f64,
+}
+
+/// Scores the cases by the fraction of the score earned by each case, from
+/// `0` for failed cases to `1` for accepted cases, and anything between for
+/// partial points
+pub fn calculate_score(config: &ScoringConfig, results: &[f64]) -> Result<ScoringResult> {
+    let results = results
+        .iter()
+        .map(|&result| if result.is_nan() { 0.0 } else { result.clamp(0.0, 1.0) })
+        .collect::<Vec<_>>();
+
+    match config {
+        ScoringConfig::Cases { total, weights } => {
+            let weights = match weights {
+                None => vec![1.0; results.len()],
+                Some(weights) if weights.len() == results.len() => weights.clone(),
+                Some(weights) => bail!(
+                    "The count of the weights {} does not match the count of the cases {}",
+                    weights.len(),
+                    results.len()
+                ),
+            };
+
+            let total_weight = weights.iter().sum::<f64>();
+            let cases =
+                results
+                    .iter()
+                    .zip(weights)
+                    .map(|(result, weight)| {
+                        if total_weight > 0.0 {
+                            total * weight / total_weight * result
+                        } else {
+                            0.0
+                        }
+                    })
+                    .collect::<Vec<_>>();
+            Ok(ScoringResult { score: cases.iter().sum(), cases: Some(cases), subtasks: vec![] })
+        }
+        ScoringConfig::Subtasks { subtasks: configs } => {
+            let mut passed = Vec::with_capacity(configs.len());
+            let mut subtasks = Vec::with_capacity(configs.len());
+            for (index, config) in configs.iter().enumerate() {
+                if config.cases.is_empty() {
+                    bail!("The subtask {index} has no cases");
+                }
+                if let Some(case) = config.cases.iter().find(|&&case| case >= results.len()) {
+                    bail!("The case {case} of the subtask {index} does not exist");
+                }
+                if config.depends.iter().any(|&dependency| dependency >= index) {
+                    bail!("The subtask {index} can only depend on the subtasks before it");
+                }
+
+                let fractions = config.cases.iter().map(|&case| results[case]);
+                let fraction = match config.rule {
+                    SubtaskRule::Min => fractions.fold(1.0, f64::min),
+                    SubtaskRule::Sum => fractions.sum::<f64>() / config.cases.len() as f64,
+                };
+                let fraction = if config.depends.iter().all(|&dependency| passed[dependency]) {
+                    fraction
+                } else {
+                    0.0
+                };
+
+                passed.push(fraction >= 1.0);
+                subtasks.push(SubtaskScore {
+                    name: config.name.clone(),
+                    score: config.score * fraction,
+                    full_score: config.score,
+                });
+            }
+
+            Ok(ScoringResult {
+                score: subtasks.iter().map(|subtask| subtask.score).sum(),
+                cases: None,
+                subtasks,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::entities::ScoringConfig;
+
+    #[test]
+    fn test_calculate_score() {
+        let config: ScoringConfig =
+            serde_yaml::from_str("{ total: 60, weights: [1, 2, 3] }").unwrap();
+        let result = super::calculate_score(&config, &[1.0, 0.5, 0.0]).unwrap();
+        assert_eq!(json!(result), json!({ "score": 20.0, "cases": [10.0, 10.0, 0.0] }));
+        assert!(super::calculate_score(&config, &[1.0]).is_err());
+
+        let config: ScoringConfig = serde_yaml::from_str(
+            r#"
+            subtasks:
+              - { name: sample, score: 10, cases: [0] }
+              - { score: 30, cases: [1, 2], rule: sum }
+              - { score: 60, cases: [1, 3], depends: [0] }
+              - { score: 0, cases: [3], depends: [1] }
+            "#,
+        )
+        .unwrap();
+        let result = super::calculate_score(&config, &[1.0, 1.0, 0.5, 1.0]).unwrap();
+        assert_eq!(
+            json!(result),
+            json!({
+                "score": 92.5,
+                "subtasks": [
+                    { "name": "sample", "score": 10.0, "full_score": 10.0 },
+                    { "score": 22.5, "full_score": 30.0 },
+                    { "score": 60.0, "full_score": 60.0 },
+                    { "score": 0.0, "full_score": 0.0 },
+                ]
+            })
+        );
+
+        let result = super::calculate_score(&config, &[0.0, 1.0, 1.0, 1.0]).unwrap();
+        assert_eq!(result.score, 30.0);
+
+        for subtasks in ["[{ score: 1, cases: [4] }]", "[{ score: 1, cases: [0], depends: [0] }]"] {
+            let config: ScoringConfig =
+                serde_yaml::from_str(&format!("{{ subtasks: {subtasks} }}")).unwrap();
+            assert!(super::calculate_score(&config, &[1.0; 4]).is_err(), "case {subtasks}");
+        }
+        assert!(serde_yaml::from_str::<ScoringConfig>("{ subtasks: [], total: 1 }").is_err());
+    }
+}
@@ -1,12 +1,26 @@
 use crate::entities::run_container::{ExecutionReport, ExecutionStatus};
 
+/// The exit codes of the checkers following the convention of testlib, other
+/// non-zero exit codes are treated as wrong answers like `diff`
+const CHECKER_PRESENTATION_ERROR: i64 = 2;
+const CHECKER_FAIL: i64 = 3;
+const CHECKER_POINTS: i64 = 7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OjStatus {
     Accepted,
+    CompileError,
     TimeLimitExceeded,
+    IdlenessLimitExceeded,
     MemoryLimitExceeded,
     WrongAnswer,
+    PresentationError,
+    PartiallyCorrect,
     RuntimeError,
     OutputLimitExceeded,
+    Skipped,
+    /// The checker failed, which is not caused by the submitted program
+    JudgementFailed,
     InternalError,
 }
 
@@ -14,48 +28,131 @@ impl From<OjStatus> for &'static str {
     fn from(status: OjStatus) -> Self {
         match status {
             OjStatus::Accepted => "ACC",
+            OjStatus::CompileError => "CE",
             OjStatus::TimeLimitExceeded => "TLE",
+            OjStatus::IdlenessLimitExceeded => "ILE",
             OjStatus::MemoryLimitExceeded => "MLE",
             OjStatus::WrongAnswer => "WA",
+            OjStatus::PresentationError => "PE",
+            OjStatus::PartiallyCorrect => "PC",
             OjStatus::RuntimeError => "RE",
             OjStatus::OutputLimitExceeded => "OLE",
+            OjStatus::Skipped => "SKIPPED",
+            OjStatus::JudgementFailed => "JF",
             OjStatus::InternalError => "INTERNAL",
         }
     }
 }
 
 pub fn get_oj_status(run_report: ExecutionReport, compare_report: ExecutionReport) -> OjStatus {
-    if matches!(run_report.status, ExecutionStatus::Normal)
-        && matches!(compare_report.status, ExecutionStatus::RuntimeError)
-    {
-        return OjStatus::WrongAnswer;
+    if !matches!(run_report.status, ExecutionStatus::Normal) {
+        return get_run_oj_status(&run_report);
     }
 
-    if matches!(
-        (&run_report.status, &compare_report.status),
-        (&ExecutionStatus::Normal, &ExecutionStatus::Normal)
-    ) {
-        return OjStatus::Accepted;
-    }
+    get_checker_oj_status(&compare_report)
+}
 
-    if matches!(
-        run_report.status,
-        ExecutionStatus::UserTimeLimitExceeded | ExecutionStatus::WallTimeLimitExceeded
-    ) {
-        return OjStatus::TimeLimitExceeded;
+/// Gets the status by the report of running the program only, for the cases
+/// where the comparison is skipped since the program failed
+pub fn get_run_oj_status(run_report: &ExecutionReport) -> OjStatus {
+    match run_report.status {
+        ExecutionStatus::Normal => OjStatus::InternalError,
+        ExecutionStatus::UserTimeLimitExceeded => OjStatus::TimeLimitExceeded,
+        // A program reaching the wall time limit while using less than half of
+        // the time on the CPU is mostly idle, such as waiting for the input.
+        // Otherwise it is busy but slowed down, e.g. by a loaded host.
+        ExecutionStatus::WallTimeLimitExceeded => {
+            let cpu_time_ms = run_report.cpu_user_time_ms + run_report.cpu_kernel_time_ms;
+            if cpu_time_ms * 2 < run_report.wall_time_ms {
+                OjStatus::IdlenessLimitExceeded
+            } else {
+                OjStatus::TimeLimitExceeded
+            }
+        }
+        ExecutionStatus::OutputLimitExceeded => OjStatus::OutputLimitExceeded,
+        ExecutionStatus::MemoryLimitExceeded => OjStatus::MemoryLimitExceeded,
+        _ => OjStatus::RuntimeError,
     }
+}
 
-    if matches!(run_report.status, ExecutionStatus::OutputLimitExceeded) {
-        return OjStatus::OutputLimitExceeded;
+/// Gets the status by the report of the checker, a checker which crashes or
+/// exceeds its limits fails the judgement instead of the submitted program
+pub fn get_checker_oj_status(compare_report: &ExecutionReport) -> OjStatus {
+    match compare_report.status {
+        ExecutionStatus::Normal => OjStatus::Accepted,
+        ExecutionStatus::RuntimeError => match compare_report.exit_code {
+            CHECKER_PRESENTATION_ERROR => OjStatus::PresentationError,
+            CHECKER_FAIL => OjStatus::JudgementFailed,
+            CHECKER_POINTS => OjStatus::PartiallyCorrect,
+            _ => OjStatus::WrongAnswer,
+        },
+        _ => OjStatus::JudgementFailed,
     }
+}
 
-    if matches!(run_report.status, ExecutionStatus::MemoryLimitExceeded) {
-        return OjStatus::MemoryLimitExceeded;
-    }
+#[cfg(test)]
+mod tests {
+    use super::OjStatus;
+    use crate::entities::run_container::{ExecutionReport, ExecutionStatus};
 
-    if !matches!(run_report.status, ExecutionStatus::Normal) {
-        return OjStatus::RuntimeError;
+    fn report(status: ExecutionStatus, exit_code: i64) -> ExecutionReport {
+        ExecutionReport {
+            status,
+            exit_code,
+            signal: None,
+            wall_time_ms: 0,
+            cpu_user_time_ms: 0,
+            cpu_kernel_time_ms: 0,
+            memory_usage_kib: 0,
+        }
     }
 
-    OjStatus::InternalError
+    #[test]
+    fn test_get_oj_status() {
+        use crate::entities::run_container::runj::ContainerExecutionStatus::*;
+
+        let normal = report(Normal, 0);
+        for (compare, status) in [
+            (report(Normal, 0), OjStatus::Accepted),
+            (report(RuntimeError, 1), OjStatus::WrongAnswer),
+            (report(RuntimeError, 2), OjStatus::PresentationError),
+            (report(RuntimeError, 3), OjStatus::JudgementFailed),
+            (report(RuntimeError, 4), OjStatus::WrongAnswer),
+            (report(RuntimeError, 7), OjStatus::PartiallyCorrect),
+            (report(SignalTerminate, 0), OjStatus::JudgementFailed),
+            (report(UserTimeLimitExceeded, 0), OjStatus::JudgementFailed),
+        ] {
+            assert_eq!(
+                super::get_oj_status(normal.clone(), compare.clone()),
+                status,
+                "{compare:?}"
+            );
+        }
+
+        for (run, status) in [
+            (report(UserTimeLimitExceeded, 0), OjStatus::TimeLimitExceeded),
+            (
+                ExecutionReport {
+                    wall_time_ms: 1000,
+                    cpu_user_time_ms: 100,
+                    ..report(WallTimeLimitExceeded, 0)
+                },
+                OjStatus::IdlenessLimitExceeded,
+            ),
+            (
+                ExecutionReport {
+                    wall_time_ms: 1000,
+                    cpu_user_time_ms: 900,
+                    ..report(WallTimeLimitExceeded, 0)
+                },
+                OjStatus::TimeLimitExceeded,
+            ),
+            (report(MemoryLimitExceeded, 0), OjStatus::MemoryLimitExceeded),
+            (report(OutputLimitExceeded, 0), OjStatus::OutputLimitExceeded),
+            (report(SignalTerminate, 0), OjStatus::RuntimeError),
+            (report(RuntimeError, 1), OjStatus::RuntimeError),
+        ] {
+            assert_eq!(super::get_oj_status(run.clone(), normal.clone()), status, "{run:?}");
+        }
+    }
 }
@@ -1,11 +1,17 @@
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use std::{collections::HashSet, path::PathBuf, sync::Arc};
 
 use anyhow::{Context, Result, bail};
+use indexmap::IndexMap;
+use serde_yaml::Value;
 use tracing::instrument;
 
-use crate::entities::{
-    ParallelTasks, RootTaskNode, SequenceTasks, Submission, SubmissionConfig, TaskConfig,
-    TaskConfigExt, TaskNode, TaskNodeExt,
+use crate::{
+    entities::{
+        MatrixTasks, ParallelTasks, Predicate, RootTaskNode, SequenceTasks, Submission,
+        SubmissionConfig, TaskConfig, TaskConfigExt, TaskNode, TaskNodeExt,
+    },
+    interpolate::{interpolate, references},
+    shared::priority::DEFAULT_PRIORITY,
 };
 
 #[instrument(skip_all)]
@@ -18,7 +24,13 @@ pub fn resolve_submission(
             resolve_sequence(".", &config.tasks).context("Error resolving root sequence tasks")?,
         ],
     });
-    Ok(Submission { id: config.id.clone(), root_directory, config, root_node })
+    Ok(Submission {
+        id: config.id.clone(),
+        priority: config.priority.unwrap_or(DEFAULT_PRIORITY),
+        root_directory,
+        config,
+        root_node,
+    })
 }
 
 fn resolve_sequence(name_prefix: &str, tasks: &SequenceTasks) -> Result<Arc<TaskNode>> {
@@ -26,104 +38,331 @@ fn resolve_sequence(name_prefix: &str, tasks: &SequenceTasks) -> Result<Arc<Task
         bail!("Empty steps provided");
     }
 
-    let mut nodes: HashMap<String, TaskNode> = HashMap::default();
-    let mut children: HashMap<String, Vec<String>> = HashMap::default();
+    let parents = resolve_parents(tasks)?;
+    let mut children = vec![vec![]; tasks.len()];
+    for (i, indexes) in parents.iter().enumerate() {
+        for &index in indexes {
+            children[index].push(i);
+        }
+    }
 
-    let root_node = {
-        let (name, root_task) = tasks.first().unwrap();
-        resolve_task(format!("{name_prefix}{}", name), root_task.clone())?
-    };
-    nodes.insert(root_node.name.clone(), root_node.clone());
+    let order = topological_order(&parents, &children);
+    if order.len() < tasks.len() {
+        let names = tasks
+            .keys()
+            .enumerate()
+            .filter(|(i, _)| !order.contains(i))
+            .map(|(_, name)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        bail!("Circular dependencies found in the `needs` field of: {names}");
+    }
+
+    let mut nodes = tasks
+        .iter()
+        .zip(&parents)
+        .enumerate()
+        .map(|(i, ((name, task), indexes))| {
+            let mut node = resolve_task(format!("{name_prefix}{}", name), task.clone())?;
+            bind_steps(&mut node, name, tasks, &ancestors(&parents, i))?;
 
-    let mut previous_node = root_node.clone();
-    for (i, (name, task)) in tasks.iter().enumerate().skip(1) {
-        let node = resolve_task(format!("{name_prefix}{}", name), task.clone())?;
+            if indexes.len() > 1 && node.when.reads_previous_value() {
+                bail!(
+                    "The `when` field of {name} cannot read `status`, `tags`, `report` or \
+                     `outputs` through `previous` when multiple tasks are needed, use \
+                     `steps.<name>` instead"
+                );
+            }
+            node.parents = indexes.iter().map(|&index| tasks[index].clone()).collect();
 
+            Ok(Some(node))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // Build the nodes from the leaves, so a task needed by several tasks is shared
+    // among them
+    let mut resolved: Vec<Option<Arc<TaskNode>>> = vec![None; tasks.len()];
+    for &i in order.iter().rev() {
+        let mut node = nodes[i].take().unwrap();
+        node.children = children[i].iter().map(|&child| resolved[child].clone().unwrap()).collect();
+        resolved[i] = Some(Arc::new(node));
+    }
+
+    Ok(resolved[0].take().unwrap())
+}
+
+/// Resolves the indexes of the predecessors of each task. A task without the
+/// `needs` field follows the closest task declared before it which does not
+/// have the field either.
+fn resolve_parents(tasks: &SequenceTasks) -> Result<Vec<Vec<usize>>> {
+    let mut parents = Vec::with_capacity(tasks.len());
+    let mut previous = None;
+    for (i, (name, task)) in tasks.iter().enumerate() {
         match &task.needs {
             None => {
-                children.entry(previous_node.name).or_default().push(node.name.clone());
-                previous_node = node.clone();
+                parents.push(previous.into_iter().collect());
+                previous = Some(i);
+            }
+            Some(_) if i == 0 => {
+                bail!("The first task cannot have the `needs` field: {name}")
             }
             Some(needs) => {
-                let exists = tasks
-                    .iter()
-                    .take(i)
-                    .any(|(task_name, _)| task_name == needs && task_name != name);
-                if !exists {
-                    bail!("Unknown task specified by the `needs` field: {needs}")
+                if needs.names().is_empty() {
+                    bail!("The `needs` field of {name} cannot be empty");
                 }
 
-                let needs_name = format!("{name_prefix}{needs}");
-                children.entry(needs_name).or_default().push(node.name.clone());
+                let mut indexes = vec![];
+                for needs_name in needs.names() {
+                    let Some(index) = tasks.get_index_of(needs_name) else {
+                        bail!("Unknown task specified by the `needs` field of {name}: {needs_name}")
+                    };
+                    if index == i {
+                        bail!("The `needs` field of {name} cannot reference the task itself");
+                    }
+                    if !indexes.contains(&index) {
+                        indexes.push(index);
+                    }
+                }
+                parents.push(indexes);
             }
-        };
+        }
+    }
+
+    Ok(parents)
+}
 
-        nodes.insert(node.name.clone(), node);
+/// The indexes of the tasks which always finish before the task, following the
+/// predecessors transitively
+fn ancestors(parents: &[Vec<usize>], index: usize) -> HashSet<usize> {
+    let mut ancestors = HashSet::new();
+    let mut stack = parents[index].clone();
+    while let Some(i) = stack.pop() {
+        if ancestors.insert(i) {
+            stack.extend(&parents[i]);
+        }
     }
+    ancestors
+}
+
+/// Kahn's algorithm, tasks on a cycle are left out of the returned order
+fn topological_order(parents: &[Vec<usize>], children: &[Vec<usize>]) -> Vec<usize> {
+    let mut degrees = parents.iter().map(Vec::len).collect::<Vec<_>>();
+    let mut order = (0..degrees.len()).filter(|&i| degrees[i] == 0).collect::<Vec<_>>();
 
-    Ok(Arc::new(append_children(root_node, &nodes, &children)))
+    let mut position = 0;
+    while let Some(&i) = order.get(position) {
+        position += 1;
+        for &child in &children[i] {
+            degrees[child] -= 1;
+            if degrees[child] == 0 {
+                order.push(child);
+            }
+        }
+    }
+
+    order
 }
 
-fn resolve_task(name: String, config: Arc<TaskConfig>) -> Result<TaskNode> {
-    Ok(match &config.ext {
-        TaskConfigExt::Sequence(ext) => {
-            let prefix = format!("{name}.");
-            let ext = TaskNodeExt::Schedule({
-                vec![
-                    resolve_sequence(&prefix, &ext.tasks)
-                        .context("Error resolving sequence tasks")?,
-                ]
-            });
-            TaskNode { name, config, children: vec![], ext }
-        }
-        TaskConfigExt::Parallel(ext) => {
-            let ext = TaskNodeExt::Schedule(match &ext.tasks {
-                ParallelTasks::Anonymous(tasks) => tasks
-                    .iter()
-                    .enumerate()
-                    .map(|(i, task)| {
-                        resolve_task(format!("{name}.{i}"), task.clone()).map(Arc::new)
-                    })
-                    .collect::<Result<_>>()
-                    .context("Error resolving anonymous parallel tasks")?,
-                ParallelTasks::Named(tasks) => tasks
-                    .iter()
-                    .map(|(task_name, task)| {
-                        resolve_task(format!("{name}.{task_name}"), task.clone()).map(Arc::new)
-                    })
-                    .collect::<Result<_>>()
-                    .context("Error resolving named parallel tasks")?,
-            });
-            TaskNode { name, config, children: vec![], ext }
+/// Binds the tasks referenced through `steps.<name>`, which must be the
+/// ancestors of the task, otherwise their status may still be changing when it
+/// is read
+fn bind_steps(
+    node: &mut TaskNode,
+    name: &str,
+    tasks: &SequenceTasks,
+    ancestors: &HashSet<usize>,
+) -> Result<()> {
+    let mut steps = node
+        .when
+        .steps()
+        .into_iter()
+        .map(|step| (step.to_owned(), "the `when` field"))
+        .collect::<Vec<_>>();
+
+    if let TaskNodeExt::Action(config) = &node.ext {
+        let config = serde_yaml::to_value(&**config).context("Error serializing the action")?;
+        for reference in references(&config, "steps") {
+            let Some((step, "outputs", key)) = reference
+                .split_once('.')
+                .and_then(|(step, rest)| rest.split_once('.').map(|(kind, key)| (step, kind, key)))
+            else {
+                bail!(
+                    "Invalid placeholder in {name}: ${{{{ steps.{reference} }}}}, expected \
+                     `steps.<name>.outputs.<key>`"
+                );
+            };
+            if tasks.get(step).is_some_and(|task| !task.outputs.contains_key(key)) {
+                bail!("The task {step} referenced by {name} does not declare the output: {key}");
+            }
+            steps.push((step.to_owned(), "the placeholders"));
         }
-        TaskConfigExt::Action(ext) => {
-            let ext = TaskNodeExt::Action(Arc::new(ext.clone()));
-            TaskNode { name, config, children: vec![], ext }
+    }
+
+    for (step, source) in steps {
+        if step == name {
+            bail!("The task {name} cannot reference itself in {source}");
         }
-    })
+
+        let Some((index, _, config)) = tasks.get_full(&step) else {
+            bail!("Unknown task specified by {source} of {name}: {step}");
+        };
+        if !ancestors.contains(&index) {
+            bail!(
+                "The task {step} referenced by {source} of {name} must be one of its ancestors \
+                 through the `needs` field"
+            );
+        }
+        node.steps.insert(step, config.clone());
+    }
+
+    Ok(())
 }
 
-fn append_children(
-    mut node: TaskNode,
-    nodes: &HashMap<String, TaskNode>,
-    children: &HashMap<String, Vec<String>>,
-) -> TaskNode {
-    if let Some(names) = children.get(&node.name) {
-        node.children = names
-            .iter()
-            .map(|name| {
-                let node = nodes.get(name).unwrap().clone();
-                append_children(node, nodes, children)
+/// The maximum number of tasks a matrix can generate
+const MAX_MATRIX_TASKS: usize = 1024;
+
+/// Generates a task for each combination of the matrix values, named by joining
+/// the values with `-`
+fn expand_matrix<'a>(
+    name: &str,
+    tasks: &'a MatrixTasks,
+) -> Result<&'a IndexMap<String, Arc<TaskConfig>>> {
+    let count = tasks
+        .matrix
+        .values()
+        .try_fold(1usize, |count, values| count.checked_mul(values.len()))
+        .filter(|count| *count <= MAX_MATRIX_TASKS);
+    if count.is_none() {
+        bail!("The matrix of {name} generates more than {MAX_MATRIX_TASKS} tasks");
+    }
+
+    let mut combinations = vec![IndexMap::new()];
+    for (key, values) in &tasks.matrix {
+        combinations = combinations
+            .into_iter()
+            .flat_map(|combination| {
+                values.iter().map(move |value| {
+                    let mut combination = combination.clone();
+                    combination.insert(key.clone(), value.clone());
+                    combination
+                })
             })
-            .map(Arc::new)
             .collect();
     }
-    node
+    if tasks.matrix.is_empty() || combinations.is_empty() {
+        bail!("The matrix of {name} does not produce any task");
+    }
+
+    let mut expanded = IndexMap::with_capacity(combinations.len());
+    for variables in combinations {
+        let task_name = variables
+            .values()
+            .map(|value| {
+                let value = match value {
+                    Value::String(value) => value.clone(),
+                    Value::Number(value) => value.to_string(),
+                    Value::Bool(value) => value.to_string(),
+                    _ => bail!("The matrix values of {name} must be strings, numbers or booleans"),
+                };
+                // Both would make the names ambiguous, or unaddressable by
+                // the dotted names of the tasks
+                if value.contains(['-', '.']) {
+                    bail!("The matrix values of {name} cannot contain `-` or `.`: {value}");
+                }
+                Ok(value)
+            })
+            .collect::<Result<Vec<_>>>()?
+            .join("-");
+
+        let mut task = tasks.task.clone();
+        interpolate(&mut task, "matrix", &variables)
+            .with_context(|| format!("Error interpolating the matrix task {name}.{task_name}"))?;
+        let task = serde_yaml::from_value(task)
+            .with_context(|| format!("Error parsing the matrix task {name}.{task_name}"))?;
+        if expanded.insert(task_name.clone(), Arc::new(task)).is_some() {
+            bail!("Duplicate matrix task name in {name}: {task_name}");
+        }
+    }
+
+    Ok(tasks.tasks.get_or_init(|| expanded))
+}
+
+fn resolve_task(name: String, config: Arc<TaskConfig>) -> Result<TaskNode> {
+    if !config.outputs.is_empty() && !matches!(config.ext, TaskConfigExt::Action(_)) {
+        bail!("The `outputs` field of {name} is only supported by action tasks");
+    }
+
+    if let Some(retry) = &config.retry {
+        if !matches!(config.ext, TaskConfigExt::Action(_)) {
+            bail!("The `retry` field of {name} is only supported by action tasks");
+        }
+        if retry.max_attempts == 0 {
+            bail!("The `max_attempts` of the `retry` field of {name} must be positive");
+        }
+        if !retry.backoff_factor.is_finite() || retry.backoff_factor < 1.0 {
+            bail!(
+                "The `backoff_factor` of the `retry` field of {name} must be a finite number of \
+                 at least 1"
+            );
+        }
+    }
+
+    let when = match &config.when {
+        None => Predicate::default(),
+        Some(when) => when
+            .parse()
+            .with_context(|| format!("Error parsing the `when` field of {name}: {when}"))?,
+    };
+
+    let ext = match &config.ext {
+        TaskConfigExt::Sequence(ext) => {
+            let prefix = format!("{name}.");
+            TaskNodeExt::Schedule(vec![
+                resolve_sequence(&prefix, &ext.tasks).context("Error resolving sequence tasks")?,
+            ])
+        }
+        TaskConfigExt::Parallel(ext) if ext.max_concurrency == Some(0) => {
+            bail!("The `max_concurrency` field of {name} must be positive")
+        }
+        TaskConfigExt::Parallel(ext) => TaskNodeExt::Schedule(match &ext.tasks {
+            ParallelTasks::Anonymous(tasks) => tasks
+                .iter()
+                .enumerate()
+                .map(|(i, task)| resolve_task(format!("{name}.{i}"), task.clone()).map(Arc::new))
+                .collect::<Result<_>>()
+                .context("Error resolving anonymous parallel tasks")?,
+            ParallelTasks::Named(tasks) => tasks
+                .iter()
+                .map(|(task_name, task)| {
+                    resolve_task(format!("{name}.{task_name}"), task.clone()).map(Arc::new)
+                })
+                .collect::<Result<_>>()
+                .context("Error resolving named parallel tasks")?,
+            ParallelTasks::Matrix(tasks) => expand_matrix(&name, tasks)?
+                .iter()
+                .map(|(task_name, task)| {
+                    resolve_task(format!("{name}.{task_name}"), task.clone()).map(Arc::new)
+                })
+                .collect::<Result<_>>()
+                .context("Error resolving matrix parallel tasks")?,
+        }),
+        TaskConfigExt::Action(ext) => TaskNodeExt::Action(Arc::new(ext.clone())),
+    };
+
+    Ok(TaskNode {
+        name,
+        config,
+        when,
+        steps: Default::default(),
+        parents: vec![],
+        children: vec![],
+        ext,
+    })
 }
 
 #[cfg(test)]
 mod tests {
-    use std::fs;
+    use std::{fs, sync::Arc};
 
     use insta::glob;
 
@@ -140,4 +379,154 @@ mod tests {
             });
         });
     }
+
+    #[test]
+    fn test_resolve_fan_in() {
+        let submission = super::resolve_submission(
+            Arc::new(
+                serde_yaml::from_str(
+                    r#"
+                    steps:
+                      prepare:
+                        action: seele/noop@1
+                      compile-a:
+                        needs: prepare
+                        action: seele/noop@1
+                      compile-b:
+                        needs: prepare
+                        action: seele/noop@1
+                      link:
+                        needs: [compile-a, compile-b]
+                        action: seele/noop@1
+                    "#,
+                )
+                .unwrap(),
+            ),
+            "test".into(),
+        )
+        .unwrap();
+
+        let prepare = &submission.root_node.tasks[0];
+        let (compile_a, compile_b) = (&prepare.children[0], &prepare.children[1]);
+        assert!(Arc::ptr_eq(&compile_a.children[0], &compile_b.children[0]));
+        assert_eq!(compile_a.children[0].parents.len(), 2);
+    }
+
+    #[test]
+    fn test_resolve_invalid_needs() {
+        for (needs, when) in [
+            ("missing", "previous.ok"),
+            ("fourth", "previous.ok"),
+            ("third", "previous.ok"),
+            ("[]", "previous.ok"),
+            ("[second, fourth]", "previous.ok"),
+            ("[first, second]", "previous.status == 'SUCCESS'"),
+        ] {
+            let yaml = format!(
+                "steps:\n  first:\n    action: seele/noop@1\n  second:\n    action: \
+                 seele/noop@1\n  third:\n    needs: {needs}\n    when: \"{when}\"\n    action: \
+                 seele/noop@1\n  fourth:\n    needs: third\n    action: seele/noop@1\n"
+            );
+            let result = super::resolve_submission(
+                Arc::new(serde_yaml::from_str(&yaml).unwrap()),
+                "test".into(),
+            );
+            assert!(result.is_err(), "case {needs}");
+        }
+
+        let yaml = "steps:\n  first:\n    needs: second\n    action: seele/noop@1\n  second:\n    \
+                    action: seele/noop@1\n";
+        let result =
+            super::resolve_submission(Arc::new(serde_yaml::from_str(yaml).unwrap()), "test".into());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_resolve_invalid_retry() {
+        for retry in [
+            "{ max_attempts: 0 }",
+            "{ max_attempts: 2, backoff_factor: 0.5 }",
+            "{ max_attempts: 2, backoff_factor: .inf }",
+            "{ max_attempts: 2, backoff_factor: .nan }",
+            "{ max_attempts: 2, statuses: [UNKNOWN_STATUS] }",
+        ] {
+            let yaml = format!("steps:\n  first:\n    action: seele/noop@1\n    retry: {retry}\n");
+            let result = serde_yaml::from_str(&yaml)
+                .map_err(anyhow::Error::from)
+                .and_then(|config| super::resolve_submission(Arc::new(config), "test".into()));
+            assert!(result.is_err(), "case {retry}");
+        }
+
+        let yaml = r#"
+            steps:
+              first:
+                retry: { max_attempts: 2 }
+                steps:
+                  second:
+                    action: seele/noop@1
+        "#;
+        let result =
+            super::resolve_submission(Arc::new(serde_yaml::from_str(yaml).unwrap()), "test".into());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_resolve_invalid_outputs() {
+        for (outputs, path) in [
+            ("{ n: { report: test } }", "${{ steps.first }}"),
+            ("{ n: { report: test } }", "${{ steps.first.status }}"),
+            ("{ n: { report: test } }", "${{ steps.first.outputs.missing }}"),
+            ("{ n: { report: test } }", "${{ steps.missing.outputs.n }}"),
+            ("{ n: { report: test } }", "${{ steps.second.outputs.n }}"),
+        ] {
+            let yaml = format!(
+                    "steps:\n  first:\n    action: seele/noop@1\n    outputs: {outputs}\n  \
+                     second:\n    action: seele/add-file@1\n    files:\n      - path: \
+                     \"{path}\"\n        plain: test\n"
+                );
+            let result = super::resolve_submission(
+                Arc::new(serde_yaml::from_str(&yaml).unwrap()),
+                "test".into(),
+            );
+            assert!(result.is_err(), "case {path}");
+        }
+
+        let yaml = r#"
+            steps:
+              first:
+                outputs: { n: { report: test } }
+                steps:
+                  second:
+                    action: seele/noop@1
+        "#;
+        let result =
+            super::resolve_submission(Arc::new(serde_yaml::from_str(yaml).unwrap()), "test".into());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_resolve_invalid_matrix() {
+        for parallel in [
+            "{ matrix: {}, task: { action: seele/noop@1 } }",
+            "{ matrix: { case: [] }, task: { action: seele/noop@1 } }",
+            "{ matrix: { case: [[1]] }, task: { action: seele/noop@1 } }",
+            "{ matrix: { case: [1, 1] }, task: { action: seele/noop@1 } }",
+            "{ matrix: { case: [a-b, a], lang: [c, b-c] }, task: { action: seele/noop@1 } }",
+            "{ matrix: { case: [1.5] }, task: { action: seele/noop@1 } }",
+            "{ matrix: { case: [-1] }, task: { action: seele/noop@1 } }",
+            "{ matrix: { case: [1] }, task: { action: seele/noop@1, test: '${{ matrix.x }}' } }",
+            &format!(
+                "{{ matrix: {{ a: {cases}, b: {cases}, c: {cases} }}, task: {{ action: \
+                 seele/noop@1 }} }}",
+                cases = serde_json::to_string(&(0..11).collect::<Vec<_>>()).unwrap()
+            ),
+        ] {
+            let yaml = format!("steps:\n  first:\n    parallel: {parallel}\n");
+            let result = super::resolve_submission(
+                Arc::new(serde_yaml::from_str(&yaml).unwrap()),
+                "test".into(),
+            );
+            assert!(result.is_err(), "case {parallel}");
+        }
+    }
 }
@@ -0,0 +1,197 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, LazyLock},
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use moka::sync::Cache;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    conf,
+    entities::{ActionTaskConfig, Submission, TaskNode, TaskNodeExt},
+    interpolate,
+    worker::{add_file::digest_file_item, run_container::read_image_index},
+};
+
+/// The fields not affecting the result of the submission
+const IGNORED_FIELDS: [&str; 4] = ["id", "tracing_attribute", "priority", "vars"];
+
+static CACHE: LazyLock<Cache<Box<[u8]>, Arc<CachedReport>>> = LazyLock::new(|| {
+    let config = &conf::CONFIG.composer.result_cache;
+    Cache::builder()
+        .name("seele-result")
+        .weigher(|_, value: &Arc<CachedReport>| -> u32 { value.weight })
+        .max_capacity(1024 * 1024 * config.cache_size_mib)
+        .time_to_idle(Duration::from_secs(60 * 60 * config.cache_ttl_hour))
+        .build()
+});
+
+#[derive(Debug)]
+pub struct CachedReport {
+    pub status: Value,
+    pub report: Option<Value>,
+    weight: u32,
+}
+
+pub fn get(key: &[u8]) -> Option<Arc<CachedReport>> {
+    CACHE.get(key)
+}
+
+pub fn write(key: Box<[u8]>, status: Value, report: Option<Value>) {
+    let weight = [Some(&status), report.as_ref()]
+        .into_iter()
+        .flatten()
+        .map(|value| serde_json::to_vec(value).map_or(0, |data| data.len()))
+        .sum::<usize>()
+        .try_into()
+        .unwrap_or(u32::MAX);
+    CACHE.insert(key, Arc::new(CachedReport { status, report, weight }))
+}
+
+/// Computes the key of the submission from its normalized config, the content
+/// of the files added from outside the submission and the identities of the
+/// images, so a submission with the same key yields the same result. Returns
+/// `None` if some image is not pulled yet, whose identity is unknown until the
+/// submission pulls it.
+pub async fn submission_key(
+    config_yaml: &str,
+    submission: &Submission,
+) -> Result<Option<Box<[u8]>>> {
+    let mut hasher = Sha256::new();
+
+    let mut config = serde_yaml::from_str(config_yaml).context("Error parsing the config")?;
+    interpolate::interpolate_submission_vars(&mut config)?;
+    if let serde_yaml::Value::Mapping(config) = &mut config {
+        for field in IGNORED_FIELDS {
+            config.remove(field);
+        }
+    }
+    // The order of the fields is kept since the order of the steps matters
+    hasher.update(serde_json::to_vec(&config).context("Error normalizing the config")?);
+
+    let mut actions = BTreeMap::new();
+    for node in &submission.root_node.tasks {
+        collect_actions(node, &mut actions);
+    }
+
+    for (name, config) in actions {
+        let image = match &*config {
+            ActionTaskConfig::Noop(_) => continue,
+            ActionTaskConfig::AddFile(config) => {
+                for item in &config.files {
+                    if let Some(digest) = digest_file_item(&item.ext)
+                        .await
+                        .with_context(|| format!("Error computing the digest of {item}"))?
+                    {
+                        hasher.update(name);
+                        hasher.update(item.path.as_os_str().as_encoded_bytes());
+                        hasher.update(digest);
+                    }
+                }
+                continue;
+            }
+            ActionTaskConfig::RunContainer(config) => &config.image,
+            ActionTaskConfig::RunJudgeCompile(config) => &config.run_container_config.image,
+            ActionTaskConfig::RunJudgeRun(config) => &config.run_container_config.image,
+        };
+
+        // An image not pulled yet is only identified after the submission
+        // pulls it, so the key would not match any later lookup
+        let Some(index) = read_image_index(image).await else {
+            return Ok(None);
+        };
+        hasher.update(name);
+        hasher.update(index);
+    }
+
+    Ok(Some(hasher.finalize().to_vec().into_boxed_slice()))
+}
+
+fn collect_actions<'a>(node: &'a TaskNode, actions: &mut BTreeMap<&'a str, Arc<ActionTaskConfig>>) {
+    if actions.contains_key(node.name.as_str()) {
+        return;
+    }
+
+    match &node.ext {
+        TaskNodeExt::Action(config) => {
+            actions.insert(&node.name, config.clone());
+        }
+        TaskNodeExt::Schedule(tasks) => {
+            for task in tasks {
+                collect_actions(task, actions);
+            }
+        }
+    }
+
+    for child in &node.children {
+        collect_actions(child, actions);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::submission_key;
+    use crate::{parse::parse_submission, resolve::resolve_submission};
+
+    async fn key(config_yaml: &str) -> Box<[u8]> {
+        let config = parse_submission(config_yaml).unwrap();
+        let submission = resolve_submission(config, "test".into()).unwrap();
+        submission_key(config_yaml, &Arc::new(submission)).await.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_submission_key() {
+        let base = key(r#"
+            id: first
+            steps:
+              prepare:
+                action: seele/add-file@1
+                files:
+                  - path: main.c
+                    plain: "${{ vars.code }}"
+              run:
+                action: seele/noop@1
+                test: 1
+            vars:
+              code: int main() {}
+            "#)
+        .await;
+
+        // The id, the priority and the vars are normalized
+        let same = key(r#"
+            id: second
+            priority: 3
+            steps:
+              prepare:
+                action: seele/add-file@1
+                files:
+                  - path: main.c
+                    plain: int main() {}
+              run:
+                action: seele/noop@1
+                test: 1
+            "#)
+        .await;
+        assert_eq!(base, same);
+
+        let changed = key(r#"
+            id: first
+            steps:
+              prepare:
+                action: seele/add-file@1
+                files:
+                  - path: main.c
+                    plain: int main() { return 1; }
+              run:
+                action: seele/noop@1
+                test: 1
+            "#)
+        .await;
+        assert_ne!(base, changed);
+    }
+}
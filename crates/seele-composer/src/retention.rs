@@ -0,0 +1,355 @@
+use std::{
+    cmp::Reverse,
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::Duration,
+};
+
+use anyhow::{Context, Result, bail};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    fs,
+    process::{ChildStdout, Command},
+    time::interval,
+};
+use tracing::{debug, info, warn};
+
+use crate::{
+    conf::{self, RetentionConfig, RetentionPolicy},
+    entities::{SubmissionConfig, TaskStatus, UtcTimestamp},
+    signal::{SubmissionReportSignal, SubmissionSignalExt},
+};
+
+const GARBAGE_COLLECTION_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubmissionOutcome {
+    /// The submission ended with an error
+    Error,
+
+    /// The submission reached the deadline, was cancelled or has failed tasks
+    Failure,
+
+    Success,
+}
+
+impl SubmissionOutcome {
+    /// Returns the outcome of the finished submission, or `None` if it is
+    /// served from the result cache and has nothing to keep
+    pub fn of(config: &SubmissionConfig, ext: &SubmissionSignalExt) -> Option<Self> {
+        Some(match ext {
+            SubmissionSignalExt::Completed(SubmissionReportSignal { cached: true, .. }) => {
+                return None;
+            }
+            SubmissionSignalExt::Error(_) => Self::Error,
+            SubmissionSignalExt::Cancelled(_)
+            | SubmissionSignalExt::Completed(SubmissionReportSignal { timeout: true, .. }) => {
+                Self::Failure
+            }
+            _ if config
+                .tasks
+                .values()
+                .any(|task| matches!(*task.status.read().unwrap(), TaskStatus::Failed { .. })) =>
+            {
+                Self::Failure
+            }
+            _ => Self::Success,
+        })
+    }
+
+    fn is_kept_by(self, policy: RetentionPolicy) -> bool {
+        match policy {
+            RetentionPolicy::Never => false,
+            RetentionPolicy::OnError => self == Self::Error,
+            RetentionPolicy::OnFailure => self != Self::Success,
+            RetentionPolicy::Always => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RetainedSubmission {
+    pub id: String,
+    pub outcome: SubmissionOutcome,
+    pub retained_at: UtcTimestamp,
+
+    #[serde(skip_deserializing)]
+    pub size_bytes: u64,
+}
+
+/// Whether the id can name a retained directory, i.e. it stays inside the
+/// retained directory and cannot be taken as an option of `tar`
+fn is_valid_id(id: &str) -> bool {
+    !matches!(id, "" | ".")
+        && !id.contains(['/', '\0'])
+        && !id.contains("..")
+        && !id.starts_with('-')
+}
+
+#[inline]
+fn retained_path(id: &str) -> PathBuf {
+    conf::PATHS.retained.join(id)
+}
+
+#[inline]
+fn metadata_path(id: &str) -> PathBuf {
+    conf::PATHS.retained.join(format!("{id}.json"))
+}
+
+/// Moves the directory of the finished submission to the retained directory if
+/// the retention policy keeps it, returns whether the directory is kept
+pub async fn retain_submission(
+    id: &str,
+    submission_root: &Path,
+    outcome: SubmissionOutcome,
+) -> bool {
+    if !outcome.is_kept_by(conf::CONFIG.composer.retention.keep) {
+        return false;
+    }
+    if !is_valid_id(id) {
+        warn!(id, "Not retaining the submission with an invalid id");
+        return false;
+    }
+
+    let result = async {
+        let target = retained_path(id);
+        if fs::metadata(&target).await.is_ok() {
+            fs::remove_dir_all(&target)
+                .await
+                .context("Error removing the previously retained directory")?;
+        }
+        fs::rename(submission_root, &target)
+            .await
+            .context("Error moving the submission directory")?;
+
+        let metadata = RetainedSubmission {
+            id: id.to_owned(),
+            outcome,
+            retained_at: Utc::now(),
+            size_bytes: 0,
+        };
+        fs::write(metadata_path(id), serde_json::to_vec(&metadata)?)
+            .await
+            .context("Error writing the metadata")
+    }
+    .await;
+    if let Err(err) = result {
+        warn!("Error retaining the submission directory: {err:#}");
+        return false;
+    }
+
+    info!(path = %retained_path(id).display(), "Retained the submission directory");
+    if let Err(err) = collect_garbage().await {
+        warn!("Error collecting the retained submission directories: {err:#}");
+    }
+    true
+}
+
+/// Lists the retained submission directories, the newest first
+pub async fn list_retained_submissions() -> Result<Vec<RetainedSubmission>> {
+    let mut submissions = vec![];
+    let mut entries = fs::read_dir(&conf::PATHS.retained)
+        .await
+        .context("Error reading the retained directory")?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension().is_none_or(|extension| extension != "json") {
+            continue;
+        }
+
+        let Ok(mut submission) = fs::read(&path)
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|data| Ok(serde_json::from_slice::<RetainedSubmission>(&data)?))
+            .and_then(|submission| match is_valid_id(&submission.id) {
+                true => Ok(submission),
+                false => bail!("Invalid submission id: {}", submission.id),
+            })
+        else {
+            warn!(path = %path.display(), "Skipping the invalid metadata");
+            continue;
+        };
+        // The directory removed by hand is listed with no size until it expires
+        submission.size_bytes =
+            directory_size(&retained_path(&submission.id)).await.unwrap_or_default();
+        submissions.push(submission);
+    }
+
+    submissions.sort_by_key(|submission| Reverse(submission.retained_at));
+    Ok(submissions)
+}
+
+/// Archives the retained submission directory as a gzipped tarball, returns
+/// `None` if the directory is not retained
+pub async fn archive_retained_submission(id: &str) -> Result<Option<ChildStdout>> {
+    if !is_valid_id(id) {
+        return Ok(None);
+    }
+
+    let submissions = list_retained_submissions().await?;
+    if !submissions.iter().any(|submission| submission.id == id) {
+        return Ok(None);
+    }
+
+    let mut child = Command::new(&conf::CONFIG.paths.tar)
+        .arg("-czf")
+        .arg("-")
+        .arg("-C")
+        .arg(&conf::PATHS.retained)
+        .arg("--")
+        .arg(id)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("Error starting the tar process")?;
+    let Some(stdout) = child.stdout.take() else {
+        bail!("Error reading the output of the tar process");
+    };
+
+    let id = id.to_owned();
+    tokio::spawn(async move {
+        match child.wait().await {
+            Ok(status) if status.success() => {}
+            Ok(status) => warn!("The tar process archiving {id} exited with {status}"),
+            Err(err) => warn!("Error waiting for the tar process archiving {id}: {err:#}"),
+        }
+    });
+
+    Ok(Some(stdout))
+}
+
+/// Removes the retained submission directories periodically so the expired
+/// ones are removed without new submissions being retained
+pub fn spawn_garbage_collector() {
+    tokio::spawn(async {
+        let mut interval = interval(GARBAGE_COLLECTION_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(err) = collect_garbage().await {
+                warn!("Error collecting the retained submission directories: {err:#}");
+            }
+        }
+    });
+}
+
+async fn collect_garbage() -> Result<()> {
+    let submissions = list_retained_submissions().await?;
+    for id in select_garbage(&submissions, &conf::CONFIG.composer.retention) {
+        debug!(id, "Removing the retained submission directory");
+        _ = fs::remove_file(metadata_path(id)).await;
+        _ = fs::remove_dir_all(retained_path(id)).await;
+    }
+
+    Ok(())
+}
+
+/// Selects the submissions exceeding the maximum age, count or size, the
+/// submissions are sorted from the newest to the oldest
+fn select_garbage<'a>(
+    submissions: &'a [RetainedSubmission],
+    config: &RetentionConfig,
+) -> Vec<&'a str> {
+    let now = Utc::now();
+    let max_size_bytes = 1024 * 1024 * config.max_size_mib;
+
+    let mut count = 0;
+    let mut size_bytes = 0;
+    submissions
+        .iter()
+        .filter(|submission| {
+            count += 1;
+            size_bytes += submission.size_bytes;
+
+            let age = (now - submission.retained_at).to_std().unwrap_or_default();
+            age > config.max_age() || count > config.max_count || size_bytes > max_size_bytes
+        })
+        .map(|submission| submission.id.as_str())
+        .collect()
+}
+
+async fn directory_size(path: &Path) -> Result<u64> {
+    let mut size = 0;
+    let mut directories = vec![path.to_path_buf()];
+    while let Some(directory) = directories.pop() {
+        let mut entries = fs::read_dir(&directory)
+            .await
+            .with_context(|| format!("Error reading the directory: {}", directory.display()))?;
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            if metadata.is_dir() {
+                directories.push(entry.path());
+            } else {
+                size += metadata.len();
+            }
+        }
+    }
+
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeDelta, Utc};
+
+    use super::{RetainedSubmission, SubmissionOutcome, is_valid_id, select_garbage};
+    use crate::conf::{RetentionConfig, RetentionPolicy};
+
+    #[test]
+    fn test_retention_policy() {
+        use SubmissionOutcome::*;
+
+        let kept = |policy| {
+            [Error, Failure, Success]
+                .into_iter()
+                .filter(|outcome| outcome.is_kept_by(policy))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(kept(RetentionPolicy::Never), vec![]);
+        assert_eq!(kept(RetentionPolicy::OnError), vec![Error]);
+        assert_eq!(kept(RetentionPolicy::OnFailure), vec![Error, Failure]);
+        assert_eq!(kept(RetentionPolicy::Always), vec![Error, Failure, Success]);
+    }
+
+    #[test]
+    fn test_is_valid_id() {
+        for id in ["submission", "a.b", "1-2"] {
+            assert!(is_valid_id(id), "case {id}");
+        }
+        for id in ["", ".", "..", "../etc", "a/b", "-a", "--help", "a..b"] {
+            assert!(!is_valid_id(id), "case {id}");
+        }
+    }
+
+    #[test]
+    fn test_select_garbage() {
+        let now = Utc::now();
+        let submission = |id: &str, hours: i64, size_mib: u64| RetainedSubmission {
+            id: id.to_owned(),
+            outcome: SubmissionOutcome::Error,
+            retained_at: now - TimeDelta::hours(hours),
+            size_bytes: 1024 * 1024 * size_mib,
+        };
+        let config = |max_count, max_size_mib| RetentionConfig {
+            keep: RetentionPolicy::Always,
+            max_count,
+            max_size_mib,
+            max_age_hour: 24,
+        };
+
+        let submissions = [
+            submission("first", 0, 1),
+            submission("second", 1, 2),
+            submission("third", 2, 3),
+            submission("expired", 25, 1),
+        ];
+        assert_eq!(select_garbage(&submissions, &config(10, 100)), vec!["expired"]);
+        assert_eq!(select_garbage(&submissions, &config(2, 100)), vec!["third", "expired"]);
+        assert_eq!(select_garbage(&submissions, &config(10, 4)), vec!["third", "expired"]);
+        assert_eq!(select_garbage(&submissions, &config(10, 2)), vec![
+            "second", "third", "expired"
+        ]);
+    }
+}
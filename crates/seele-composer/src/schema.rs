@@ -0,0 +1,30 @@
+use schemars::{Schema, generate::SchemaSettings, transform::RecursiveTransform};
+
+use crate::entities::SubmissionConfig;
+
+/// The JSON Schema of the submission format, which is published to
+/// `docs/public/submission.schema.json`
+pub fn submission_schema() -> Schema {
+    SchemaSettings::default()
+        // The fields not serialized into the reports are marked as `writeOnly`,
+        // which means nothing to the submissions
+        .with_transform(RecursiveTransform(|schema: &mut Schema| {
+            schema.remove("writeOnly");
+        }))
+        .into_generator()
+        .into_root_schema_for::<SubmissionConfig>()
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_submission_schema() {
+        let schema = serde_json::to_string_pretty(&super::submission_schema()).unwrap();
+        assert_eq!(
+            format!("{schema}\n"),
+            include_str!("../../../docs/public/submission.schema.json"),
+            "The published schema is outdated, regenerate it with `seele schema > \
+             docs/public/submission.schema.json`"
+        );
+    }
+}
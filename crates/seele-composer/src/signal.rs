@@ -1,9 +1,9 @@
 use serde::Serialize;
 use serde_json::Value;
 
-use crate::entities::UtcTimestamp;
+use crate::{entities::UtcTimestamp, parse::SubmissionParseError};
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SubmissionSignal {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<String>,
@@ -12,20 +12,25 @@ pub struct SubmissionSignal {
     pub ext: SubmissionSignalExt,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum SubmissionSignalExt {
     Progress(SubmissionReportSignal),
     Error(SubmissionErrorSignal),
     Completed(SubmissionReportSignal),
+    Cancelled(SubmissionReportSignal),
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SubmissionErrorSignal {
     pub error: String,
+
+    /// Where the submission config is invalid if it fails to parse
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parse_error: Option<SubmissionParseError>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SubmissionReportSignal {
     pub report_at: UtcTimestamp,
 
@@ -35,15 +40,33 @@ pub struct SubmissionReportSignal {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub report_error: Option<String>,
 
+    /// Lines logged by the reporter through `console.log`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub report_logs: Vec<String>,
+
+    #[serde(skip_serializing_if = "is_false")]
+    pub timeout: bool,
+
+    /// Whether the report is served from the result cache instead of running
+    /// the submission
+    #[serde(skip_serializing_if = "is_false")]
+    pub cached: bool,
+
     pub status: Value,
 }
 
+#[inline]
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
 impl SubmissionSignalExt {
     pub fn get_type(&self) -> &'static str {
         match self {
             Self::Progress { .. } => "PROGRESS",
             Self::Error { .. } => "ERROR",
             Self::Completed { .. } => "COMPLETED",
+            Self::Cancelled { .. } => "CANCELLED",
         }
     }
 }
@@ -0,0 +1,147 @@
+use std::{collections::HashSet, path::PathBuf, sync::Arc};
+
+use anyhow::Result;
+use indexmap::IndexMap;
+use serde::Serialize;
+
+use crate::{
+    entities::{RootTaskNode, TaskNode, TaskNodeExt},
+    parse::{SubmissionParseError, parse_submission},
+    resolve,
+};
+
+/// The result of resolving a submission without running any of its tasks
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SubmissionValidation {
+    Valid(SubmissionValidReport),
+    Invalid(SubmissionInvalidReport),
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubmissionValidReport {
+    pub id: String,
+    pub root_node: Arc<RootTaskNode>,
+
+    /// The names of the tasks which each task waits for
+    pub needs: IndexMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubmissionInvalidReport {
+    pub error: String,
+
+    /// The underlying errors, from the outermost to the innermost
+    pub causes: Vec<String>,
+
+    /// Where the submission config is invalid if it fails to parse
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parse_error: Option<SubmissionParseError>,
+}
+
+impl SubmissionValidation {
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        matches!(self, Self::Valid(_))
+    }
+}
+
+/// Parses and resolves the submission, no file is created and no action runs
+pub fn validate_submission(config_yaml: &str) -> SubmissionValidation {
+    match do_validate_submission(config_yaml) {
+        Ok(report) => SubmissionValidation::Valid(report),
+        Err(err) => SubmissionValidation::Invalid(SubmissionInvalidReport {
+            error: format!("{err:#}"),
+            causes: err.chain().map(ToString::to_string).collect(),
+            parse_error: err.downcast_ref::<SubmissionParseError>().cloned(),
+        }),
+    }
+}
+
+fn do_validate_submission(config_yaml: &str) -> Result<SubmissionValidReport> {
+    let config = parse_submission(config_yaml)?;
+    // The root directory is never accessed since nothing is executed
+    let submission = resolve::resolve_submission(config, PathBuf::new())?;
+
+    let mut needs = IndexMap::new();
+    let mut visited = HashSet::new();
+    for node in &submission.root_node.tasks {
+        collect_needs(node, &mut needs, &mut visited);
+    }
+
+    Ok(SubmissionValidReport { id: submission.id, root_node: submission.root_node, needs })
+}
+
+fn collect_needs<'a>(
+    node: &'a TaskNode,
+    needs: &mut IndexMap<String, Vec<String>>,
+    visited: &mut HashSet<&'a str>,
+) {
+    if !visited.insert(&node.name) {
+        return;
+    }
+
+    needs.entry(node.name.clone()).or_default();
+    for child in &node.children {
+        needs.entry(child.name.clone()).or_default().push(node.name.clone());
+    }
+
+    if let TaskNodeExt::Schedule(tasks) = &node.ext {
+        for task in tasks {
+            collect_needs(task, needs, visited);
+        }
+    }
+    for child in &node.children {
+        collect_needs(child, needs, visited);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SubmissionValidation;
+
+    #[test]
+    fn test_validate_submission() {
+        let yaml = r#"
+            id: validate
+            steps:
+              prepare:
+                action: seele/noop@1
+              compile:
+                parallel:
+                  - action: seele/noop@1
+                  - action: seele/noop@1
+              check:
+                needs: prepare
+                action: seele/noop@1
+              run:
+                needs: [compile, check]
+                action: seele/noop@1
+        "#;
+        let SubmissionValidation::Valid(report) = super::validate_submission(yaml) else {
+            panic!("The submission should be valid");
+        };
+        assert_eq!(report.id, "validate");
+        assert_eq!(report.needs[".prepare"], Vec::<String>::new());
+        assert_eq!(report.needs[".compile"], vec![".prepare"]);
+        assert_eq!(report.needs[".compile.0"], Vec::<String>::new());
+        assert_eq!(report.needs[".check"], vec![".prepare"]);
+        assert_eq!(report.needs[".run"], vec![".compile", ".check"]);
+
+        let yaml = r#"
+            steps:
+              prepare:
+                action: seele/noop@1
+              run:
+                needs: missing
+                action: seele/noop@1
+        "#;
+        let SubmissionValidation::Invalid(report) = super::validate_submission(yaml) else {
+            panic!("The submission should be invalid");
+        };
+        assert_eq!(report.causes.len(), 2);
+        assert!(report.causes[1].contains("missing"));
+
+        assert!(!super::validate_submission("steps: [").is_valid());
+    }
+}
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use http_cache::CacheMode;
 use serde::Deserialize;
 
@@ -22,6 +24,10 @@ pub struct ActionAddFileConfig {
 
     #[serde(default = "default_cache_ttl_hour")]
     pub cache_ttl_hour: u64,
+
+    /// The host paths which the files with local sources may be copied from
+    #[serde(default)]
+    pub local_allowlist: Vec<PathBuf>,
 }
 
 #[derive(Debug, Clone, Copy, Deserialize)]
@@ -54,6 +60,7 @@ impl Default for ActionAddFileConfig {
             cache_strategy: default_cache_strategy(),
             cache_size_mib: default_cache_size_mib(),
             cache_ttl_hour: default_cache_ttl_hour(),
+            local_allowlist: Default::default(),
         }
     }
 }
@@ -98,6 +105,11 @@ pub struct ActionRunContainerConfig {
 
     #[serde(default = "default_cache_ttl_hour")]
     pub cache_ttl_hour: u64,
+
+    /// The host paths which the mounts with absolute sources may reference,
+    /// the other mounts must stay inside the submission root
+    #[serde(default)]
+    pub mount_allowlist: Vec<PathBuf>,
 }
 
 impl Default for ActionRunContainerConfig {
@@ -111,6 +123,7 @@ impl Default for ActionRunContainerConfig {
             preload_images: Default::default(),
             cache_size_mib: default_cache_size_mib(),
             cache_ttl_hour: default_cache_ttl_hour(),
+            mount_allowlist: Default::default(),
         }
     }
 }
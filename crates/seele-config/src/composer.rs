@@ -1,4 +1,238 @@
+use std::time::Duration;
+
 use serde::Deserialize;
 
 #[derive(Debug, Deserialize, Default)]
-pub struct ComposerConfig;
+pub struct ComposerConfig {
+    #[serde(default)]
+    pub idempotency: IdempotencyConfig,
+
+    #[serde(default)]
+    pub journal: JournalConfig,
+
+    #[serde(default)]
+    pub result_cache: ResultCacheConfig,
+
+    #[serde(default)]
+    pub retention: RetentionConfig,
+
+    #[serde(default)]
+    pub reporter: ReporterConfig,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IdempotencyConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    #[serde(default = "default_result_capacity")]
+    pub result_capacity: usize,
+
+    #[serde(default = "default_result_ttl_seconds")]
+    pub result_ttl_seconds: u64,
+}
+
+impl IdempotencyConfig {
+    #[inline]
+    pub fn result_ttl(&self) -> Duration {
+        Duration::from_secs(self.result_ttl_seconds)
+    }
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            result_capacity: default_result_capacity(),
+            result_ttl_seconds: default_result_ttl_seconds(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JournalConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    #[serde(default = "default_resume_timeout_seconds")]
+    pub resume_timeout_seconds: u64,
+}
+
+impl JournalConfig {
+    #[inline]
+    pub fn resume_timeout(&self) -> Duration {
+        Duration::from_secs(self.resume_timeout_seconds)
+    }
+}
+
+impl Default for JournalConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            resume_timeout_seconds: default_resume_timeout_seconds(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResultCacheConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    #[serde(default = "default_cache_size_mib")]
+    pub cache_size_mib: u64,
+
+    #[serde(default = "default_cache_ttl_hour")]
+    pub cache_ttl_hour: u64,
+}
+
+impl Default for ResultCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cache_size_mib: default_cache_size_mib(),
+            cache_ttl_hour: default_cache_ttl_hour(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RetentionConfig {
+    #[serde(default)]
+    pub keep: RetentionPolicy,
+
+    #[serde(default = "default_retention_max_count")]
+    pub max_count: usize,
+
+    #[serde(default = "default_retention_max_size_mib")]
+    pub max_size_mib: u64,
+
+    #[serde(default = "default_retention_max_age_hour")]
+    pub max_age_hour: u64,
+}
+
+impl RetentionConfig {
+    #[inline]
+    pub fn max_age(&self) -> Duration {
+        Duration::from_secs(60 * 60 * self.max_age_hour)
+    }
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            keep: Default::default(),
+            max_count: default_retention_max_count(),
+            max_size_mib: default_retention_max_size_mib(),
+            max_age_hour: default_retention_max_age_hour(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ReporterConfig {
+    #[serde(default = "default_reporter_timeout_ms")]
+    pub timeout_ms: u64,
+
+    #[serde(default = "default_reporter_memory_limit_mib")]
+    pub memory_limit_mib: u64,
+
+    /// The progress reports of a submission are sent at most once in this
+    /// interval, the progress in between is merged into the next one
+    #[serde(default = "default_progress_interval_ms")]
+    pub progress_interval_ms: u64,
+}
+
+impl ReporterConfig {
+    #[inline]
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+
+    #[inline]
+    pub fn progress_interval(&self) -> Duration {
+        Duration::from_millis(self.progress_interval_ms)
+    }
+}
+
+impl Default for ReporterConfig {
+    fn default() -> Self {
+        Self {
+            timeout_ms: default_reporter_timeout_ms(),
+            memory_limit_mib: default_reporter_memory_limit_mib(),
+            progress_interval_ms: default_progress_interval_ms(),
+        }
+    }
+}
+
+/// Which submission directories are kept after the submissions finish
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetentionPolicy {
+    #[default]
+    Never,
+
+    /// Keeps the submissions ending with an error
+    OnError,
+
+    /// Keeps the submissions ending with an error, reaching the deadline, being
+    /// cancelled or having failed tasks
+    OnFailure,
+
+    Always,
+}
+
+#[inline]
+const fn default_result_capacity() -> usize {
+    1024
+}
+
+#[inline]
+const fn default_result_ttl_seconds() -> u64 {
+    3600
+}
+
+#[inline]
+const fn default_resume_timeout_seconds() -> u64 {
+    600
+}
+
+#[inline]
+const fn default_cache_size_mib() -> u64 {
+    64
+}
+
+#[inline]
+const fn default_cache_ttl_hour() -> u64 {
+    24
+}
+
+#[inline]
+const fn default_retention_max_count() -> usize {
+    32
+}
+
+#[inline]
+const fn default_retention_max_size_mib() -> u64 {
+    1024
+}
+
+#[inline]
+const fn default_retention_max_age_hour() -> u64 {
+    24
+}
+
+#[inline]
+const fn default_reporter_timeout_ms() -> u64 {
+    5000
+}
+
+#[inline]
+const fn default_reporter_memory_limit_mib() -> u64 {
+    64
+}
+
+#[inline]
+const fn default_progress_interval_ms() -> u64 {
+    500
+}
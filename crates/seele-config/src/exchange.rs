@@ -23,6 +23,19 @@ pub struct HttpExchangeConfig {
 
     #[serde(default = "default_max_body_size")]
     pub max_body_size_bytes: u64,
+
+    /// A separate listener for the administrative endpoints, such as
+    /// downloading the retained submissions, which are disabled without it
+    #[serde(default)]
+    pub admin: Option<HttpAdminConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HttpAdminConfig {
+    #[serde(default = "default_http_address")]
+    pub address: IpAddr,
+
+    pub port: u16,
 }
 
 #[derive(Debug, Deserialize)]
@@ -38,8 +51,20 @@ pub struct AmqpExchangeSubmissionConfig {
     pub routing_key: String,
     pub queue: String,
 
+    #[serde(default)]
+    pub cancel_routing_key: String,
+
     #[serde(default)]
     pub queue_options: lapin::options::QueueDeclareOptions,
+
+    /// Declares the queue as a priority queue, so the broker delivers the
+    /// messages of greater priorities first
+    #[serde(default)]
+    pub max_priority: Option<u8>,
+
+    /// Limits the size of the compressed messages after decompression
+    #[serde(default = "default_max_body_size")]
+    pub max_decompressed_size_bytes: u64,
 }
 
 #[derive(Debug, Clone, Deserialize)]
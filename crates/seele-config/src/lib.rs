@@ -4,9 +4,9 @@ use indexmap::IndexMap;
 use serde::Deserialize;
 use tracing_subscriber::filter::LevelFilter;
 
-pub use self::{action::*, env::*, exchange::*, image::*, path::*};
+pub use self::{action::*, composer::*, env::*, exchange::*, image::*, path::*};
 use self::{
-    composer::ComposerConfig, healthz::HealthzConfig, http::HttpConfig, telemetry::TelemetryConfig,
+    healthz::HealthzConfig, http::HttpConfig, priority::PriorityConfig, telemetry::TelemetryConfig,
     worker::WorkerConfig,
 };
 
@@ -18,6 +18,7 @@ mod healthz;
 mod http;
 mod image;
 mod path;
+mod priority;
 mod telemetry;
 mod worker;
 
@@ -49,6 +50,9 @@ pub struct SeeleConfig {
     #[serde(default)]
     pub paths: PathsConfig,
 
+    #[serde(default)]
+    pub priority: PriorityConfig,
+
     #[serde(default)]
     pub telemetry: Option<TelemetryConfig>,
 
@@ -68,23 +72,18 @@ pub struct SeeleConfig {
     pub worker: WorkerConfig,
 }
 
-#[derive(Debug, Copy, Clone, Deserialize)]
+#[derive(Debug, Default, Copy, Clone, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum LogLevel {
     Trace,
     Debug,
     Info,
+    #[default]
     Warn,
     Error,
     Off,
 }
 
-impl Default for LogLevel {
-    fn default() -> Self {
-        Self::Warn
-    }
-}
-
 impl From<LogLevel> for LevelFilter {
     fn from(val: LogLevel) -> Self {
         match val {
@@ -154,6 +153,9 @@ pub struct PathsConfig {
 
     #[serde(default = "default_umoci_path")]
     pub umoci: String,
+
+    #[serde(default = "default_tar_path")]
+    pub tar: String,
 }
 
 impl Default for PathsConfig {
@@ -164,6 +166,7 @@ impl Default for PathsConfig {
             runj: default_runj_path(),
             skopeo: default_skopeo_path(),
             umoci: default_umoci_path(),
+            tar: default_tar_path(),
         }
     }
 }
@@ -192,3 +195,8 @@ fn default_skopeo_path() -> String {
 fn default_umoci_path() -> String {
     "/usr/bin/umoci".to_string()
 }
+
+#[inline]
+fn default_tar_path() -> String {
+    "/usr/bin/tar".to_string()
+}
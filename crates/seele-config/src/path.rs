@@ -11,6 +11,8 @@ pub struct SeelePaths {
     pub images: PathBuf,
     pub temp: PathBuf,
     pub submissions: PathBuf,
+    pub journal: PathBuf,
+    pub retained: PathBuf,
 }
 
 impl SeelePaths {
@@ -28,4 +30,6 @@ pub static PATHS: LazyLock<SeelePaths> = LazyLock::new(|| SeelePaths {
     images: CONFIG.paths.root.join("images"),
     temp: CONFIG.paths.root.join("temp"),
     submissions: CONFIG.paths.tmp.join("seele").join("submissions"),
+    journal: CONFIG.paths.tmp.join("seele").join("journal"),
+    retained: CONFIG.paths.tmp.join("seele").join("retained"),
 });
@@ -0,0 +1,27 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct PriorityConfig {
+    #[serde(default = "default_aging_interval_seconds")]
+    pub aging_interval_seconds: u64,
+}
+
+impl PriorityConfig {
+    #[inline]
+    pub fn aging_interval(&self) -> Duration {
+        Duration::from_secs(self.aging_interval_seconds)
+    }
+}
+
+impl Default for PriorityConfig {
+    fn default() -> Self {
+        Self { aging_interval_seconds: default_aging_interval_seconds() }
+    }
+}
+
+#[inline]
+const fn default_aging_interval_seconds() -> u64 {
+    10
+}
@@ -6,20 +6,27 @@ use std::{
 };
 
 use anyhow::{Context, Result, bail};
-use futures_util::StreamExt;
-use lapin::{Channel, ChannelState, Connection, message::Delivery};
+use futures_util::{StreamExt, future::Either, stream};
+use lapin::{
+    BasicProperties, Channel, ChannelState, Connection, Consumer,
+    message::Delivery,
+    options::{BasicConsumeOptions, QueueDeclareOptions},
+    types::{AMQPValue, FieldTable, ShortString},
+};
 use ring_channel::ring_channel;
 use tokio::{
     sync::{Mutex, mpsc},
     time::sleep,
 };
 use tokio_graceful_shutdown::SubsystemHandle;
-use tracing::{error, info, warn};
+use tracing::{debug, error, info, warn};
 use triggered::Listener;
 
 use crate::{
-    composer::{ComposerQueueItem, ComposerQueueTx, SubmissionSignal, SubmissionSignalExt},
+    codec::{self, ContentEncoding, ContentType},
+    composer::{self, ComposerQueueItem, ComposerQueueTx, SubmissionSignal, SubmissionSignalExt},
     conf::{self, AmqpExchangeConfig, AmqpExchangeReportConfig},
+    shared::priority::DEFAULT_PRIORITY,
 };
 
 static STATUS_MAP: LazyLock<Mutex<HashMap<String, bool>>> = LazyLock::new(Default::default);
@@ -116,12 +123,13 @@ async fn do_consume(
         .await
         .context("Error declaring the submission exchange")?;
 
+    let mut queue_arguments = FieldTable::default();
+    if let Some(max_priority) = config.submission.max_priority {
+        queue_arguments.insert("x-max-priority".into(), AMQPValue::ShortShortUInt(max_priority));
+    }
+
     channel
-        .queue_declare(
-            &config.submission.queue,
-            config.submission.queue_options,
-            Default::default(),
-        )
+        .queue_declare(&config.submission.queue, config.submission.queue_options, queue_arguments)
         .await
         .context("Error declaring the queue")?;
 
@@ -163,11 +171,19 @@ async fn do_consume(
         .await
         .context("Error consuming the channel")?;
 
+    let mut cancel_consumer = match config.submission.cancel_routing_key.as_str() {
+        "" => Either::Right(stream::pending()),
+        routing_key => Either::Left(
+            create_cancel_consumer(&channel, &config.submission.exchange.name, routing_key).await?,
+        ),
+    };
+
     {
         let mut map = STATUS_MAP.lock().await;
         map.insert(name.to_owned(), true);
     }
 
+    let max_decompressed_size_bytes = config.submission.max_decompressed_size_bytes;
     let report_config = Arc::new(config.report.clone());
     loop {
         tokio::select! {
@@ -176,10 +192,19 @@ async fn do_consume(
                 None => break,
                 Some(Err(err)) => bail!("Failed to consume from the queue: {err:#}"),
                 Some(Ok(delivery)) => {
-                    if let Err(err) = handle_delivery(&tx, delivery, channel.clone(), report_config.clone()).await {
+                    if let Err(err) = handle_delivery(&tx, delivery, channel.clone(), report_config.clone(), max_decompressed_size_bytes).await {
                         error!("Error handling the delivery: {err:#}");
                     }
                 }
+            },
+            result = cancel_consumer.next() => match result {
+                None => break,
+                Some(Err(err)) => bail!("Failed to consume from the cancel queue: {err:#}"),
+                Some(Ok(delivery)) => {
+                    if let Err(err) = handle_cancel_delivery(delivery).await {
+                        error!("Error handling the cancel delivery: {err:#}");
+                    }
+                }
             }
         }
     }
@@ -191,17 +216,91 @@ async fn do_consume(
     Ok(())
 }
 
+/// Each instance consumes cancellations from its own exclusive queue, since the
+/// submission can be running in any of them
+async fn create_cancel_consumer(
+    channel: &Channel,
+    exchange: &str,
+    routing_key: &str,
+) -> Result<Consumer> {
+    let queue = channel
+        .queue_declare(
+            "",
+            QueueDeclareOptions { exclusive: true, auto_delete: true, ..Default::default() },
+            Default::default(),
+        )
+        .await
+        .context("Error declaring the cancel queue")?;
+
+    channel
+        .queue_bind(
+            queue.name().as_str(),
+            exchange,
+            routing_key,
+            Default::default(),
+            Default::default(),
+        )
+        .await
+        .context("Error binding the cancel queue to the exchange")?;
+
+    channel
+        .basic_consume(
+            queue.name().as_str(),
+            &format!("seele-cancel-{}", nano_id::base62::<6>()),
+            BasicConsumeOptions { no_ack: true, ..Default::default() },
+            Default::default(),
+        )
+        .await
+        .context("Error consuming the cancel queue")
+}
+
+async fn handle_cancel_delivery(delivery: Delivery) -> Result<()> {
+    let id = String::from_utf8(delivery.data)?;
+    let id = id.trim();
+    if composer::cancel_submission(id).await {
+        info!("Cancelling the submission {id}");
+    } else {
+        debug!("Ignoring the cancellation of an unknown submission: {id}");
+    }
+    Ok(())
+}
+
 async fn handle_delivery(
     tx: &ComposerQueueTx,
     delivery: Delivery,
     channel: Arc<Channel>,
     config: Arc<AmqpExchangeReportConfig>,
+    max_decompressed_size_bytes: u64,
 ) -> Result<()> {
-    let config_yaml = String::from_utf8(delivery.data.clone())?;
+    let properties = &delivery.properties;
+    let content_type =
+        ContentType::of_submission(properties.content_type().as_ref().map(ShortString::as_str));
+    let encoding =
+        ContentEncoding::parse(properties.content_encoding().as_ref().map(ShortString::as_str))?;
+    let config_yaml = codec::decode_submission(
+        &delivery.data,
+        content_type,
+        encoding,
+        max_decompressed_size_bytes,
+    )?;
+
+    // The reports are encoded like the submission
+    let report_type = ContentType::of_report(None, content_type);
+    let mut report_properties =
+        BasicProperties::default().with_content_type(report_type.media_type().into());
+    if let Some(encoding) = encoding.name() {
+        report_properties = report_properties.with_content_encoding(encoding.into());
+    }
+
+    let priority = *delivery.properties.priority();
     let (status_tx, mut status_rx) =
         ring_channel::<SubmissionSignal>(NonZeroUsize::try_from(1).unwrap());
 
-    tx.send(ComposerQueueItem { config_yaml, status_tx }).await?;
+    tx.send(priority.unwrap_or(DEFAULT_PRIORITY), ComposerQueueItem {
+        config_yaml,
+        priority,
+        status_tx,
+    })?;
 
     tokio::spawn({
         let channel = channel.clone();
@@ -221,9 +320,10 @@ async fn handle_delivery(
                 };
 
                 let result = async {
-                    let mut data = Vec::with_capacity(4096);
-                    serde_json::to_writer(&mut data, &signal)
+                    let data = codec::encode_report(&signal, report_type, false)
                         .context("Error serializing the report")?;
+                    let data =
+                        codec::compress(data, encoding).context("Error compressing the report")?;
 
                     channel
                         .basic_publish(
@@ -231,7 +331,7 @@ async fn handle_delivery(
                             routing_key,
                             Default::default(),
                             &data,
-                            Default::default(),
+                            report_properties.clone(),
                         )
                         .await
                         .context("Error publishing the report")?
@@ -0,0 +1,304 @@
+use std::{
+    borrow::Cow,
+    io::{self, Read, Write},
+    mem,
+};
+
+use anyhow::{Context, Result, bail};
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use serde::Serialize;
+
+/// The format of the submissions and the reports carried by the exchanges
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentType {
+    Yaml,
+    Json,
+    MessagePack,
+}
+
+impl ContentType {
+    /// The format of the submission, which is YAML unless the media type says
+    /// otherwise, since the submissions are sent as YAML without a media type
+    /// by the existing clients
+    pub fn of_submission(media_type: Option<&str>) -> Self {
+        media_type.and_then(Self::from_media_type).unwrap_or(Self::Yaml)
+    }
+
+    /// Negotiates the format of the reports with the accepted media types,
+    /// falls back to the format of the submission, or JSON for YAML
+    /// submissions as the reports have always been JSON
+    pub fn of_report(accept: Option<&str>, submission: Self) -> Self {
+        let negotiated = accept.and_then(|accept| {
+            parse_weighted(accept)
+                .filter_map(|(media_type, weight)| {
+                    Some((Self::from_media_type(media_type)?, weight))
+                })
+                .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map(|(content_type, _)| content_type)
+        });
+
+        negotiated.unwrap_or(match submission {
+            Self::Yaml | Self::Json => Self::Json,
+            Self::MessagePack => Self::MessagePack,
+        })
+    }
+
+    fn from_media_type(media_type: &str) -> Option<Self> {
+        let media_type = media_type.split(';').next().unwrap_or_default().trim();
+        Some(match media_type.to_ascii_lowercase().as_str() {
+            "application/yaml" | "application/x-yaml" | "text/yaml" | "text/x-yaml" => Self::Yaml,
+            "application/json" | "text/json" => Self::Json,
+            "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => {
+                Self::MessagePack
+            }
+            _ => return None,
+        })
+    }
+
+    pub fn media_type(self) -> &'static str {
+        match self {
+            Self::Yaml => "application/yaml",
+            Self::Json => "application/json",
+            Self::MessagePack => "application/msgpack",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentEncoding {
+    Identity,
+    Gzip,
+    Zstd,
+}
+
+impl ContentEncoding {
+    pub fn parse(value: Option<&str>) -> Result<Self> {
+        Ok(match value.unwrap_or_default().trim().to_ascii_lowercase().as_str() {
+            "" | "identity" => Self::Identity,
+            "gzip" | "x-gzip" => Self::Gzip,
+            "zstd" => Self::Zstd,
+            encoding => bail!("Unsupported content encoding: {encoding}"),
+        })
+    }
+
+    /// Negotiates the encoding of the reports with the accepted encodings,
+    /// zstd is preferred if both are accepted with the same weight
+    pub fn negotiate(accept_encoding: Option<&str>) -> Self {
+        accept_encoding
+            .into_iter()
+            .flat_map(parse_weighted)
+            .filter_map(|(encoding, weight)| match Self::parse(Some(encoding)) {
+                Ok(Self::Identity) | Err(_) => None,
+                Ok(encoding) => Some((encoding, weight)),
+            })
+            .max_by(|(a, a_weight), (b, b_weight)| {
+                a_weight.total_cmp(b_weight).then((*a == Self::Zstd).cmp(&(*b == Self::Zstd)))
+            })
+            .map_or(Self::Identity, |(encoding, _)| encoding)
+    }
+
+    /// The value of the `Content-Encoding`, `None` for the identity
+    pub fn name(self) -> Option<&'static str> {
+        match self {
+            Self::Identity => None,
+            Self::Gzip => Some("gzip"),
+            Self::Zstd => Some("zstd"),
+        }
+    }
+}
+
+/// Parses the items of an `Accept` like header with their `q` weights, the
+/// items not accepted at all are skipped
+fn parse_weighted(value: &str) -> impl Iterator<Item = (&str, f32)> {
+    value.split(',').filter_map(|item| {
+        let mut parts = item.split(';').map(str::trim);
+        let name = parts.next().filter(|name| !name.is_empty())?;
+        let weight = parts
+            .find_map(|param| param.strip_prefix("q="))
+            .map_or(Some(1.0), |weight| weight.parse::<f32>().ok())?;
+        (weight > 0.0).then_some((name, weight))
+    })
+}
+
+/// Decodes the body of a submission into the config parsed by the composer.
+/// JSON is kept as it is since it is parsed as YAML, so the parse errors still
+/// point to its lines, and MessagePack is converted to JSON.
+pub fn decode_submission(
+    data: &[u8],
+    content_type: ContentType,
+    encoding: ContentEncoding,
+    max_size_bytes: u64,
+) -> Result<String> {
+    let data = match encoding {
+        ContentEncoding::Identity => Cow::Borrowed(data),
+        ContentEncoding::Gzip => Cow::Owned(decompress(GzDecoder::new(data), max_size_bytes)?),
+        ContentEncoding::Zstd => Cow::Owned(decompress(
+            zstd::Decoder::new(data).context("Error creating the zstd decoder")?,
+            max_size_bytes,
+        )?),
+    };
+
+    Ok(match content_type {
+        ContentType::Yaml | ContentType::Json => {
+            String::from_utf8(data.into_owned()).context("The submission is not valid UTF-8")?
+        }
+        ContentType::MessagePack => {
+            let value: serde_json::Value = rmp_serde::from_slice(&data)
+                .context("Error decoding the MessagePack submission")?;
+            serde_json::to_string(&value)?
+        }
+    })
+}
+
+fn decompress(reader: impl Read, max_size_bytes: u64) -> Result<Vec<u8>> {
+    let mut data = vec![];
+    reader
+        .take(max_size_bytes.saturating_add(1))
+        .read_to_end(&mut data)
+        .context("Error decompressing the submission")?;
+    if data.len() as u64 > max_size_bytes {
+        bail!("The size of the decompressed submission exceeds the limit: {max_size_bytes}");
+    }
+    Ok(data)
+}
+
+/// Encodes a report, the reports of a submission are concatenated as JSON
+/// lines, YAML documents or MessagePack values
+pub fn encode_report(
+    value: &impl Serialize,
+    content_type: ContentType,
+    pretty: bool,
+) -> Result<Vec<u8>> {
+    Ok(match content_type {
+        ContentType::Json => {
+            let mut data =
+                if pretty { serde_json::to_vec_pretty(value)? } else { serde_json::to_vec(value)? };
+            data.push(b'\n');
+            data
+        }
+        ContentType::Yaml => format!("---\n{}", serde_yaml::to_string(value)?).into_bytes(),
+        ContentType::MessagePack => rmp_serde::to_vec_named(value)?,
+    })
+}
+
+/// Compresses a stream of chunks, each chunk is flushed so the receiver gets
+/// the report without waiting for the following ones
+pub enum StreamEncoder {
+    Identity,
+    Gzip(GzEncoder<Vec<u8>>),
+    Zstd(zstd::Encoder<'static, Vec<u8>>),
+}
+
+impl StreamEncoder {
+    pub fn new(encoding: ContentEncoding) -> io::Result<Self> {
+        Ok(match encoding {
+            ContentEncoding::Identity => Self::Identity,
+            ContentEncoding::Gzip => Self::Gzip(GzEncoder::new(vec![], Compression::default())),
+            ContentEncoding::Zstd => Self::Zstd(zstd::Encoder::new(vec![], 0)?),
+        })
+    }
+
+    pub fn encode(&mut self, chunk: Vec<u8>) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Identity => Ok(chunk),
+            Self::Gzip(encoder) => {
+                encoder.write_all(&chunk)?;
+                encoder.flush()?;
+                Ok(mem::take(encoder.get_mut()))
+            }
+            Self::Zstd(encoder) => {
+                encoder.write_all(&chunk)?;
+                encoder.flush()?;
+                Ok(mem::take(encoder.get_mut()))
+            }
+        }
+    }
+
+    pub fn finish(self) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Identity => Ok(vec![]),
+            Self::Gzip(encoder) => encoder.finish(),
+            Self::Zstd(encoder) => encoder.finish(),
+        }
+    }
+}
+
+pub fn compress(data: Vec<u8>, encoding: ContentEncoding) -> io::Result<Vec<u8>> {
+    let mut encoder = StreamEncoder::new(encoding)?;
+    let mut compressed = encoder.encode(data)?;
+    compressed.extend(encoder.finish()?);
+    Ok(compressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+    use serde_json::json;
+
+    use super::{ContentEncoding, ContentType, StreamEncoder, compress, decode_submission};
+
+    #[test]
+    fn test_negotiate() {
+        use ContentType::*;
+
+        assert_eq!(ContentType::of_submission(None), Yaml);
+        assert_eq!(ContentType::of_submission(Some("application/x-www-form-urlencoded")), Yaml);
+        assert_eq!(ContentType::of_submission(Some("application/json; charset=utf-8")), Json);
+        assert_eq!(ContentType::of_report(None, Yaml), Json);
+        assert_eq!(ContentType::of_report(Some("*/*"), MessagePack), MessagePack);
+        assert_eq!(
+            ContentType::of_report(Some("application/json;q=0.5, application/yaml"), Json),
+            Yaml
+        );
+
+        assert_eq!(ContentEncoding::negotiate(None), ContentEncoding::Identity);
+        assert_eq!(ContentEncoding::negotiate(Some("gzip, deflate, br")), ContentEncoding::Gzip);
+        assert_eq!(ContentEncoding::negotiate(Some("gzip, zstd")), ContentEncoding::Zstd);
+        assert_eq!(ContentEncoding::negotiate(Some("gzip, zstd;q=0")), ContentEncoding::Gzip);
+        assert!(ContentEncoding::parse(Some("br")).is_err());
+    }
+
+    #[test]
+    fn test_decode_submission() {
+        let submission = json!({ "id": "test", "steps": { "run": { "action": "seele/noop@1" } } });
+        let data = rmp_serde::to_vec(&submission).unwrap();
+
+        for encoding in [ContentEncoding::Identity, ContentEncoding::Gzip, ContentEncoding::Zstd] {
+            let compressed = compress(data.clone(), encoding).unwrap();
+            let config = decode_submission(
+                &compressed,
+                ContentType::MessagePack,
+                encoding,
+                data.len() as u64,
+            )
+            .unwrap();
+            assert_eq!(serde_yaml::from_str::<serde_json::Value>(&config).unwrap(), submission);
+        }
+
+        let compressed = compress(data.clone(), ContentEncoding::Zstd).unwrap();
+        assert!(
+            decode_submission(&compressed, ContentType::MessagePack, ContentEncoding::Zstd, 8)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_stream_encoder() {
+        let mut encoder = StreamEncoder::new(ContentEncoding::Gzip).unwrap();
+        let first = encoder.encode(b"first\n".to_vec()).unwrap();
+
+        // The flushed chunk is decodable before the stream is finished
+        let mut decoded = String::new();
+        _ = GzDecoder::new(&first[..]).read_to_string(&mut decoded);
+        assert_eq!(decoded, "first\n");
+
+        let mut data = first;
+        data.extend(encoder.encode(b"second\n".to_vec()).unwrap());
+        data.extend(encoder.finish().unwrap());
+        let mut decoded = String::new();
+        GzDecoder::new(&data[..]).read_to_string(&mut decoded).unwrap();
+        assert_eq!(decoded, "first\nsecond\n");
+    }
+}
@@ -1,23 +1,27 @@
-use std::{convert::Infallible, net::SocketAddr, num::NonZeroUsize, time::Duration};
+use std::{net::SocketAddr, num::NonZeroUsize, time::Duration};
 
 use anyhow::{Result, bail};
 use axum::{
-    Router,
+    Json, Router,
     body::{Body, HttpBody, to_bytes},
-    extract::Request,
-    http::StatusCode,
+    extract::{Path, Request},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
     response::{IntoResponse, Response},
-    routing::any,
+    routing::{any, delete, get, post},
 };
-use futures_util::StreamExt;
+use futures_util::{StreamExt, stream};
 use ring_channel::ring_channel;
+use serde::Serialize;
 use tokio::{net::TcpListener, time::sleep};
 use tokio_graceful_shutdown::SubsystemHandle;
+use tokio_util::io::ReaderStream;
 use tracing::{error, info};
 
 use crate::{
-    composer::{ComposerQueueItem, ComposerQueueTx, SubmissionSignal, SubmissionSignalExt},
+    codec::{self, ContentEncoding, ContentType, StreamEncoder},
+    composer::{self, ComposerQueueItem, ComposerQueueTx, SubmissionSignalExt},
     conf::HttpExchangeConfig,
+    shared::priority::DEFAULT_PRIORITY,
 };
 
 pub async fn run(
@@ -26,41 +30,111 @@ pub async fn run(
     tx: ComposerQueueTx,
     config: &HttpExchangeConfig,
 ) -> Result<()> {
-    let app = Router::new().route(
-        "/",
-        any({
-            let tx = tx.clone();
-            let max_body_size_bytes = config.max_body_size_bytes;
-            move |request: Request| handle_submission_request(request, tx, max_body_size_bytes)
-        }),
-    );
+    let app = Router::new()
+        .route(
+            "/",
+            any({
+                let tx = tx.clone();
+                let max_body_size_bytes = config.max_body_size_bytes;
+                move |request: Request| handle_submission_request(request, tx, max_body_size_bytes)
+            }),
+        )
+        .route("/submissions/{id}", delete(handle_cancel_request))
+        .route("/schema", get(handle_schema_request))
+        .route(
+            "/validate",
+            post({
+                let max_body_size_bytes = config.max_body_size_bytes;
+                move |request: Request| handle_validate_request(request, max_body_size_bytes)
+            }),
+        );
 
     let addr = SocketAddr::from((config.address, config.port));
     let listener = TcpListener::bind(addr).await?;
 
     info!("Starting http exchange {} on {}:{}", name, config.address, config.port);
 
-    axum::serve(listener, app)
-        .with_graceful_shutdown(async move {
-            handle.on_shutdown_requested().await;
+    let shutdown = handle.create_cancellation_token();
+    let serve = axum::serve(listener, app).with_graceful_shutdown({
+        let shutdown = shutdown.clone();
+        async move {
+            shutdown.cancelled().await;
 
             info!("Http exchange is shutting down, waiting for unfinished submissions");
             sleep(Duration::from_secs(5)).await;
-        })
-        .await?;
+        }
+    });
+
+    // The retained submissions contain the sources and the testcases of
+    // everyone, so they are never served to the submitters
+    let Some(admin) = &config.admin else {
+        serve.await?;
+        return Ok(());
+    };
+
+    let admin_app = Router::new()
+        .route("/retained", get(handle_retained_list_request))
+        .route("/retained/{id}", get(handle_retained_archive_request));
+    let admin_addr = SocketAddr::from((admin.address, admin.port));
+    let admin_listener = TcpListener::bind(admin_addr).await?;
+
+    info!(
+        "Starting the admin endpoints of http exchange {} on {}:{}",
+        name, admin.address, admin.port
+    );
+
+    let admin_serve = axum::serve(admin_listener, admin_app)
+        .with_graceful_shutdown(async move { shutdown.cancelled().await });
+    tokio::try_join!(serve, admin_serve)?;
 
     Ok(())
 }
 
-fn serialize(debug: bool, signal: &SubmissionSignal) -> String {
-    let result =
-        if debug { serde_json::to_string_pretty(signal) } else { serde_json::to_string(signal) };
-    match result {
+fn serialize(debug: bool, content_type: ContentType, value: &impl Serialize) -> Vec<u8> {
+    match codec::encode_report(value, content_type, debug) {
         Err(err) => {
             error!("Error serializing the value: {:#}", err);
-            "".to_string()
+            vec![]
         }
-        Ok(json) => format!("{}\n", json),
+        Ok(data) => data,
+    }
+}
+
+#[inline]
+fn header_str(headers: &HeaderMap, name: header::HeaderName) -> Option<&str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+/// The formats of the submission in the request and the reports in the
+/// response, negotiated by the headers of the request
+#[derive(Debug, Clone, Copy)]
+struct Negotiation {
+    submission: ContentType,
+    report: ContentType,
+    report_encoding: ContentEncoding,
+}
+
+impl Negotiation {
+    fn of(headers: &HeaderMap) -> Self {
+        let submission = ContentType::of_submission(header_str(headers, header::CONTENT_TYPE));
+        Self {
+            submission,
+            report: ContentType::of_report(header_str(headers, header::ACCEPT), submission),
+            report_encoding: ContentEncoding::negotiate(header_str(
+                headers,
+                header::ACCEPT_ENCODING,
+            )),
+        }
+    }
+
+    fn report_response(self, body: Body) -> Response {
+        let mut response = Response::new(body);
+        let headers = response.headers_mut();
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(self.report.media_type()));
+        if let Some(encoding) = self.report_encoding.name() {
+            headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static(encoding));
+        }
+        response
     }
 }
 
@@ -78,34 +152,124 @@ async fn handle_submission_request(
     }
 }
 
+async fn handle_cancel_request(Path(id): Path<String>) -> impl IntoResponse {
+    if composer::cancel_submission(&id).await {
+        info!("Cancelling the submission {id}");
+        StatusCode::ACCEPTED
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+async fn handle_retained_list_request() -> impl IntoResponse {
+    match composer::list_retained_submissions().await {
+        Ok(submissions) => Json(submissions).into_response(),
+        Err(err) => {
+            error!("Error listing the retained submissions: {:#}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+        }
+    }
+}
+
+async fn handle_retained_archive_request(Path(id): Path<String>) -> impl IntoResponse {
+    match composer::archive_retained_submission(&id).await {
+        Ok(Some(archive)) => (
+            [
+                (header::CONTENT_TYPE, "application/gzip".to_string()),
+                (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{id}.tar.gz\"")),
+            ],
+            Body::from_stream(ReaderStream::new(archive)),
+        )
+            .into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(err) => {
+            error!("Error archiving the retained submission: {:#}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+        }
+    }
+}
+
+async fn handle_validate_request(request: Request, max_body_size_bytes: u64) -> impl IntoResponse {
+    let debug = matches!(request.uri().query(), Some(query) if query.contains("debug"));
+    let negotiation = Negotiation::of(request.headers());
+    let result = async {
+        let config_yaml =
+            read_request_body(request, negotiation.submission, max_body_size_bytes).await?;
+        let validation = composer::validate_submission(&config_yaml);
+        let data = codec::compress(
+            serialize(debug, negotiation.report, &validation),
+            negotiation.report_encoding,
+        )?;
+        anyhow::Ok((validation.is_valid(), data))
+    }
+    .await;
+
+    match result {
+        Ok((valid, data)) => {
+            let status = if valid { StatusCode::OK } else { StatusCode::UNPROCESSABLE_ENTITY };
+            (status, negotiation.report_response(Body::from(data))).into_response()
+        }
+        Err(err) => {
+            error!("Error handling the validate request: {:#}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+        }
+    }
+}
+
+async fn handle_schema_request() -> impl IntoResponse {
+    Json(composer::submission_schema())
+}
+
+async fn read_request_body(
+    request: Request,
+    content_type: ContentType,
+    max_body_size_bytes: u64,
+) -> Result<String> {
+    let body_size = request.body().size_hint().upper().unwrap_or(max_body_size_bytes + 1);
+    if body_size > max_body_size_bytes {
+        bail!("The size of the request body exceeds the limit: {}", body_size);
+    }
+
+    let encoding = ContentEncoding::parse(header_str(request.headers(), header::CONTENT_ENCODING))?;
+    let data = to_bytes(request.into_body(), usize::MAX).await?;
+    codec::decode_submission(&data, content_type, encoding, max_body_size_bytes)
+}
+
 async fn handle_submission_request_inner(
     request: Request,
     tx: ComposerQueueTx,
     max_body_size_bytes: u64,
 ) -> Result<Response> {
-    {
-        let body_size = request.body().size_hint().upper().unwrap_or(max_body_size_bytes + 1);
-        if body_size > max_body_size_bytes {
-            bail!("The size of the request body exceeds the limit: {}", body_size);
-        }
-    }
-
     let show_progress = matches!(request.uri().query(), Some(query) if query.contains("progress"));
     let debug = matches!(request.uri().query(), Some(query) if query.contains("debug"));
+    let negotiation = Negotiation::of(request.headers());
     let config_yaml =
-        { String::from_utf8(to_bytes(request.into_body(), usize::MAX).await?.to_vec())? };
+        read_request_body(request, negotiation.submission, max_body_size_bytes).await?;
+    let encoder = StreamEncoder::new(negotiation.report_encoding)?;
     let (status_tx, status_rx) = ring_channel(NonZeroUsize::try_from(1).unwrap());
-    tx.send(ComposerQueueItem { config_yaml, status_tx }).await?;
+    tx.send(DEFAULT_PRIORITY, ComposerQueueItem { config_yaml, priority: None, status_tx })?;
 
-    let stream = status_rx.map(move |signal| {
-        type CallbackResult = Result<String, Infallible>;
+    // The encoder is finished after the last signal to complete the compressed
+    // stream, then the response ends
+    let stream = stream::unfold(
+        (status_rx, Some(encoder)),
+        move |(mut status_rx, mut encoder)| async move {
+            let chunk = loop {
+                let current = encoder.as_mut()?;
+                let Some(signal) = status_rx.next().await else {
+                    break encoder.take()?.finish();
+                };
 
-        if !show_progress && matches!(signal.ext, SubmissionSignalExt::Progress { .. }) {
-            return CallbackResult::Ok("".to_string());
-        }
+                if !show_progress && matches!(signal.ext, SubmissionSignalExt::Progress { .. }) {
+                    continue;
+                }
 
-        CallbackResult::Ok(serialize(debug, &signal))
-    });
+                break current.encode(serialize(debug, negotiation.report, &signal));
+            };
+
+            Some((chunk, (status_rx, encoder)))
+        },
+    );
 
-    Ok(Response::new(Body::from_stream(stream)))
+    Ok(negotiation.report_response(Body::from_stream(stream)))
 }
@@ -1,18 +1,19 @@
 use anyhow::Result;
-use seele_composer::{self as composer, ComposerQueueItem};
+use seele_composer::{self as composer, ComposerQueueTx};
 use seele_config::{self as conf, ExchangeConfig};
-use tokio::sync::mpsc;
+use seele_shared as shared;
 use tokio_graceful_shutdown::{SubsystemBuilder, SubsystemHandle};
 use tracing::info;
 
 pub use self::amqp::is_amqp_healthy;
 
 mod amqp;
+mod codec;
 mod http;
 
 pub async fn exchange_main(
     handle: SubsystemHandle,
-    composer_queue_tx: mpsc::Sender<ComposerQueueItem>,
+    composer_queue_tx: ComposerQueueTx,
 ) -> Result<()> {
     info!("Initializing exchanges based on the configuration");
 
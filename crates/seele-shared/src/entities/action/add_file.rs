@@ -1,13 +1,15 @@
 use std::{fmt::Display, path::PathBuf};
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+#[schemars(rename = "AddFileConfig")]
 pub struct Config {
     pub files: Vec<FileItem>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct FileItem {
     pub path: PathBuf,
 
@@ -15,7 +17,7 @@ pub struct FileItem {
     pub ext: FileItemExt,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 #[serde(untagged)]
 pub enum FileItemExt {
     Http { url: String },
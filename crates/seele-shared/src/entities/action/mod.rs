@@ -1,10 +1,11 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 pub mod add_file;
 pub mod noop;
 pub mod run_container;
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 #[serde(tag = "action")]
 pub enum ActionTaskConfig {
     #[serde(rename = "seele/noop@1")]
@@ -3,7 +3,8 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use anyhow::{Result, bail};
+use anyhow::{Context, Result, bail};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 pub mod run_judge;
@@ -12,11 +13,15 @@ pub mod runj;
 use runj::RlimitItem;
 use seele_config::OciImage;
 
+use crate::file;
+
 pub type ExecutionReport = runj::ContainerExecutionReport;
 pub type ExecutionStatus = runj::ContainerExecutionStatus;
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+#[schemars(rename = "RunContainerConfig")]
 pub struct Config {
+    #[schemars(with = "String")]
     pub image: OciImage,
 
     #[serde(default = "default_cwd")]
@@ -33,6 +38,11 @@ pub struct Config {
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub mounts: Vec<MountConfig>,
 
+    /// The mounts added by the worker itself, such as the directories of
+    /// `run_judge`, which are not checked like `mounts`
+    #[serde(skip)]
+    pub trusted_mounts: Vec<runj::MountConfig>,
+
     #[serde(default)]
     pub limits: LimitsConfig,
 }
@@ -42,7 +52,7 @@ fn default_cwd() -> PathBuf {
     "/".into()
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 #[serde(untagged)]
 pub enum CommandConfig {
     Simple(String),
@@ -69,7 +79,7 @@ impl TryInto<Vec<String>> for CommandConfig {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 #[serde(untagged)]
 pub enum MountConfig {
     Simple(String),
@@ -77,23 +87,29 @@ pub enum MountConfig {
 }
 
 impl MountConfig {
-    pub fn into_runj_mount(self, parent_path_absolute: &Path) -> Result<runj::MountConfig> {
-        Ok(match self {
+    /// Converts the mount, whose source is resolved inside the submission root
+    /// if relative, or must be inside one of the allowed host paths if absolute
+    pub fn into_runj_mount(
+        self,
+        submission_root: &Path,
+        allowed_host_paths: &[PathBuf],
+    ) -> Result<runj::MountConfig> {
+        let mut config = match self {
             Self::Simple(config) => {
                 let parts: Vec<_> = config.split(':').collect();
                 match parts[..] {
                     [item] => runj::MountConfig {
-                        from: parent_path_absolute.join(item),
+                        from: item.into(),
                         to: ["/", item].iter().collect(),
                         options: None,
                     },
                     [from, to] => runj::MountConfig {
-                        from: parent_path_absolute.join(from),
+                        from: from.into(),
                         to: ["/", to].iter().collect(),
                         options: None,
                     },
                     [from, to, options] => runj::MountConfig {
-                        from: parent_path_absolute.join(from),
+                        from: from.into(),
                         to: ["/", to].iter().collect(),
                         options: Some(options.split(',').map(|s| s.to_string()).collect()),
                     },
@@ -101,11 +117,19 @@ impl MountConfig {
                 }
             }
             Self::Full(config) => config,
-        })
+        };
+
+        config.from = if config.from.is_absolute() {
+            file::resolve_allowed_path(&config.from, allowed_host_paths)
+                .context("Error resolving the mount source")?
+        } else {
+            file::resolve_path(submission_root, &config.from)?
+        };
+        Ok(config)
     }
 }
 
-#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema, Serialize)]
 pub struct LimitsConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub time_ms: Option<u64>,
@@ -149,3 +173,41 @@ impl From<LimitsConfig> for runj::LimitsConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+
+    use super::MountConfig;
+
+    #[test]
+    fn test_into_runj_mount() {
+        let root = env::temp_dir().join(format!("seele-mount-{:x}", rand::random::<u64>()));
+        let shared =
+            env::temp_dir().join(format!("seele-mount-shared-{:x}", rand::random::<u64>()));
+        fs::create_dir_all(root.join("nested")).unwrap();
+        fs::create_dir_all(&shared).unwrap();
+        let allowlist = [shared.clone()];
+
+        let mount = MountConfig::Simple("nested:/data:ro".to_owned())
+            .into_runj_mount(&root, &allowlist)
+            .unwrap();
+        assert_eq!(mount.from, root.canonicalize().unwrap().join("nested"));
+        assert_eq!(mount.to, PathBuf::from("/data"));
+
+        let mount = MountConfig::Simple(format!("{}:/shared", shared.display()))
+            .into_runj_mount(&root, &allowlist)
+            .unwrap();
+        assert_eq!(mount.from, shared.canonicalize().unwrap());
+
+        for config in ["../nested:/data", "/etc:/etc", "/etc/passwd"] {
+            assert!(
+                MountConfig::Simple(config.to_owned()).into_runj_mount(&root, &allowlist).is_err(),
+                "case {config}"
+            );
+        }
+
+        fs::remove_dir_all(&root).unwrap();
+        fs::remove_dir_all(&shared).unwrap();
+    }
+}
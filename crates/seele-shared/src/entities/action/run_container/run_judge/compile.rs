@@ -1,28 +1,34 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use super::MountFile;
 use crate::entities::run_container;
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+#[schemars(rename = "RunJudgeCompileConfig")]
 pub struct Config {
     #[serde(flatten)]
     pub run_container_config: run_container::Config,
 
     #[serde(default)]
+    #[schemars(with = "Vec<String>")]
     pub sources: Vec<MountFile>,
 
     #[serde(default)]
     pub saves: Vec<String>,
 
+    // The default value depends on the worker config, keep it out of the schema
     #[serde(default)]
+    #[schemars(skip_serializing)]
     pub cache: CacheConfig,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct CacheConfig {
     pub enabled: bool,
 
     #[serde(default = "default_max_allowed_size_mib")]
+    #[schemars(skip_serializing)]
     pub max_allowed_size_mib: u64,
 
     #[serde(default)]
@@ -1,13 +1,16 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use super::MountFile;
 use crate::entities::run_container;
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+#[schemars(rename = "RunJudgeRunConfig")]
 pub struct Config {
     #[serde(flatten)]
     pub run_container_config: run_container::Config,
 
     #[serde(default)]
+    #[schemars(with = "Vec<String>")]
     pub files: Vec<MountFile>,
 }
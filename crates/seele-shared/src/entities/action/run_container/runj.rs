@@ -1,5 +1,6 @@
 use std::{fmt::Display, path::PathBuf};
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -46,7 +47,7 @@ pub struct OverlayfsConfig {
     pub merged_dir: PathBuf,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct FdConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stdin: Option<PathBuf>,
@@ -64,7 +65,8 @@ pub struct FdConfig {
     pub stderr_to_stdout: bool,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+#[schemars(rename = "FullMountConfig")]
 pub struct MountConfig {
     pub from: PathBuf,
     pub to: PathBuf,
@@ -135,7 +137,7 @@ pub struct ContainerExecutionReport {
     pub memory_usage_kib: u64,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum ContainerExecutionStatus {
     Normal,
@@ -1,7 +1,9 @@
 pub use action::*;
+pub use predicate::*;
 pub use report::*;
 pub use submission::*;
 
 mod action;
+mod predicate;
 mod report;
 mod submission;
@@ -0,0 +1,477 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{Context, Result, bail};
+use serde_json::{Number, Value};
+
+/// The parsed form of a task's `when` field
+#[derive(Debug, Clone, PartialEq)]
+pub enum Predicate {
+    Literal(Value),
+    Always,
+    Previous(PredicateField),
+    Step { name: String, field: PredicateField },
+    Not(Box<Predicate>),
+    And(Box<Predicate>, Box<Predicate>),
+    Or(Box<Predicate>, Box<Predicate>),
+    Compare { left: Box<Predicate>, operator: PredicateOperator, right: Box<Predicate> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PredicateField {
+    Ok,
+    Failed,
+    Skipped,
+    Status,
+    Tag(String),
+    Report(Vec<String>),
+    Output(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PredicateOperator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Default for Predicate {
+    fn default() -> Self {
+        Self::Previous(PredicateField::Ok)
+    }
+}
+
+impl Predicate {
+    /// Names of the tasks referenced through `steps.<name>`
+    pub fn steps(&self) -> Vec<&str> {
+        let mut names = vec![];
+        self.collect_steps(&mut names);
+        names
+    }
+
+    fn collect_steps<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match self {
+            Self::Step { name, .. } => names.push(name),
+            Self::Not(inner) => inner.collect_steps(names),
+            Self::And(left, right) | Self::Or(left, right) => {
+                left.collect_steps(names);
+                right.collect_steps(names);
+            }
+            Self::Compare { left, right, .. } => {
+                left.collect_steps(names);
+                right.collect_steps(names);
+            }
+            Self::Literal(_) | Self::Always | Self::Previous(_) => {}
+        }
+    }
+
+    /// Whether the predicate reads a value field (`status`, `tags`, `report` or
+    /// `outputs`) through `previous`, which is only meaningful when the task
+    /// has a single predecessor
+    pub fn reads_previous_value(&self) -> bool {
+        match self {
+            Self::Previous(field) => {
+                matches!(
+                    field,
+                    PredicateField::Status
+                        | PredicateField::Tag(_)
+                        | PredicateField::Report(_)
+                        | PredicateField::Output(_)
+                )
+            }
+            Self::Not(inner) => inner.reads_previous_value(),
+            Self::And(left, right) | Self::Or(left, right) | Self::Compare { left, right, .. } => {
+                left.reads_previous_value() || right.reads_previous_value()
+            }
+            Self::Literal(_) | Self::Always | Self::Step { .. } => false,
+        }
+    }
+}
+
+impl FromStr for Predicate {
+    type Err = anyhow::Error;
+
+    fn from_str(source: &str) -> Result<Self> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, position: 0 };
+        let predicate = parser.parse_or()?;
+        if let Some(token) = parser.tokens.get(parser.position) {
+            bail!("Unexpected token: {token}");
+        }
+        Ok(predicate)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Token {
+    LeftParen,
+    RightParen,
+    Dot,
+    Not,
+    And,
+    Or,
+    Operator(PredicateOperator),
+    String(String),
+    Number(Number),
+    Ident(String),
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LeftParen => write!(f, "("),
+            Self::RightParen => write!(f, ")"),
+            Self::Dot => write!(f, "."),
+            Self::Not => write!(f, "!"),
+            Self::And => write!(f, "&&"),
+            Self::Or => write!(f, "||"),
+            Self::Operator(operator) => write!(f, "{operator}"),
+            Self::String(value) => write!(f, "{value:?}"),
+            Self::Number(value) => write!(f, "{value}"),
+            Self::Ident(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+impl Display for PredicateOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            Self::Eq => "==",
+            Self::Ne => "!=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+        })
+    }
+}
+
+#[inline]
+fn is_ident_char(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || ch == '_' || ch == '-'
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = source.char_indices().peekable();
+
+    while let Some(&(i, ch)) = chars.peek() {
+        let after_dot = matches!(tokens.last(), Some(Token::Dot));
+        let token = match ch {
+            _ if ch.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            '(' | ')' | '.' => {
+                chars.next();
+                match ch {
+                    '(' => Token::LeftParen,
+                    ')' => Token::RightParen,
+                    _ => Token::Dot,
+                }
+            }
+            '&' | '|' => {
+                chars.next();
+                match chars.next() {
+                    Some((_, '&')) if ch == '&' => Token::And,
+                    Some((_, '|')) if ch == '|' => Token::Or,
+                    _ => bail!("Expected `{ch}{ch}` at position {i}"),
+                }
+            }
+            '!' | '=' | '<' | '>' => {
+                chars.next();
+                let followed_by_eq = matches!(chars.peek(), Some((_, '=')));
+                if followed_by_eq {
+                    chars.next();
+                }
+                match (ch, followed_by_eq) {
+                    ('!', false) => Token::Not,
+                    ('!', true) => Token::Operator(PredicateOperator::Ne),
+                    ('=', true) => Token::Operator(PredicateOperator::Eq),
+                    ('<', false) => Token::Operator(PredicateOperator::Lt),
+                    ('<', true) => Token::Operator(PredicateOperator::Le),
+                    ('>', false) => Token::Operator(PredicateOperator::Gt),
+                    ('>', true) => Token::Operator(PredicateOperator::Ge),
+                    _ => bail!("Expected `==` at position {i}"),
+                }
+            }
+            '"' | '\'' => {
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        None => bail!("Unterminated string starting at position {i}"),
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, escaped)) => value.push(escaped),
+                            None => bail!("Unterminated string starting at position {i}"),
+                        },
+                        Some((_, next)) if next == ch => break,
+                        Some((_, next)) => value.push(next),
+                    }
+                }
+                Token::String(value)
+            }
+            _ if !after_dot && (ch.is_ascii_digit() || ch == '-') => {
+                let mut text = String::new();
+                while let Some(&(_, next)) = chars.peek() {
+                    if !(next.is_ascii_digit() || next == '.' || (next == '-' && text.is_empty())) {
+                        break;
+                    }
+                    text.push(next);
+                    chars.next();
+                }
+                Token::Number(
+                    parse_number(&text)
+                        .with_context(|| format!("Invalid number at position {i}: {text}"))?,
+                )
+            }
+            _ if is_ident_char(ch) => {
+                let mut text = String::new();
+                while let Some(&(_, next)) = chars.peek() {
+                    if !is_ident_char(next) {
+                        break;
+                    }
+                    text.push(next);
+                    chars.next();
+                }
+                Token::Ident(text)
+            }
+            _ => bail!("Unexpected character `{ch}` at position {i}"),
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+fn parse_number(text: &str) -> Result<Number> {
+    if let Ok(value) = text.parse::<i64>() {
+        return Ok(value.into());
+    }
+
+    let value = text.parse::<f64>()?;
+    Number::from_f64(value).context("Not a finite number")
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Predicate> {
+        let mut left = self.parse_and()?;
+        while matches!(self.peek(), Some(Token::Or)) {
+            self.next();
+            left = Predicate::Or(Box::new(left), Box::new(self.parse_and()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Predicate> {
+        let mut left = self.parse_unary()?;
+        while matches!(self.peek(), Some(Token::And)) {
+            self.next();
+            left = Predicate::And(Box::new(left), Box::new(self.parse_unary()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Predicate> {
+        if matches!(self.peek(), Some(Token::Not)) {
+            self.next();
+            return Ok(Predicate::Not(Box::new(self.parse_unary()?)));
+        }
+
+        let left = self.parse_primary()?;
+        Ok(match self.peek() {
+            Some(Token::Operator(operator)) => {
+                let operator = *operator;
+                self.next();
+                Predicate::Compare {
+                    left: Box::new(left),
+                    operator,
+                    right: Box::new(self.parse_primary()?),
+                }
+            }
+            _ => left,
+        })
+    }
+
+    fn parse_primary(&mut self) -> Result<Predicate> {
+        Ok(match self.next() {
+            None => bail!("Unexpected end of the expression"),
+            Some(Token::LeftParen) => {
+                let inner = self.parse_or()?;
+                match self.next() {
+                    Some(Token::RightParen) => inner,
+                    _ => bail!("Expected `)`"),
+                }
+            }
+            Some(Token::String(value)) => Predicate::Literal(Value::String(value)),
+            Some(Token::Number(value)) => Predicate::Literal(Value::Number(value)),
+            Some(Token::Ident(ident)) => {
+                let mut segments = vec![ident];
+                while matches!(self.peek(), Some(Token::Dot)) {
+                    self.next();
+                    match self.next() {
+                        Some(Token::Ident(segment)) => segments.push(segment),
+                        _ => bail!("Expected an identifier after `{}.`", segments.join(".")),
+                    }
+                }
+                parse_path(segments)?
+            }
+            Some(token) => bail!("Unexpected token: {token}"),
+        })
+    }
+}
+
+fn parse_path(segments: Vec<String>) -> Result<Predicate> {
+    let path = segments.join(".");
+    let segments = segments.iter().map(String::as_str).collect::<Vec<_>>();
+    Ok(match segments[..] {
+        ["true"] => Predicate::Literal(Value::Bool(true)),
+        ["false"] => Predicate::Literal(Value::Bool(false)),
+        ["null"] => Predicate::Literal(Value::Null),
+        ["always"] => Predicate::Always,
+        ["previous", ref field @ ..] => Predicate::Previous(
+            parse_field(field).with_context(|| format!("Invalid path: {path}"))?,
+        ),
+        ["steps", name, ref field @ ..] => Predicate::Step {
+            name: name.to_owned(),
+            field: parse_field(field).with_context(|| format!("Invalid path: {path}"))?,
+        },
+        ["steps"] => bail!("Expected a task name after `steps.`"),
+        _ => bail!("Unknown identifier: {path}"),
+    })
+}
+
+fn parse_field(segments: &[&str]) -> Result<PredicateField> {
+    Ok(match segments {
+        ["ok"] => PredicateField::Ok,
+        ["failed"] => PredicateField::Failed,
+        ["skipped"] => PredicateField::Skipped,
+        ["status"] => PredicateField::Status,
+        ["tags", key] => PredicateField::Tag(key.to_string()),
+        ["outputs", key] => PredicateField::Output(key.to_string()),
+        ["report", path @ ..] => {
+            PredicateField::Report(path.iter().map(|segment| segment.to_string()).collect())
+        }
+        [] => bail!("Missing a field such as `ok` or `status`"),
+        [field, ..] => bail!("Unknown field: {field}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{Predicate, PredicateField, PredicateOperator};
+
+    #[test]
+    fn test_parse_literals() {
+        assert_eq!("true".parse::<Predicate>().unwrap(), Predicate::Literal(json!(true)));
+        assert_eq!("always".parse::<Predicate>().unwrap(), Predicate::Always);
+        assert_eq!(
+            "previous.ok".parse::<Predicate>().unwrap(),
+            Predicate::Previous(PredicateField::Ok)
+        );
+        assert_eq!(
+            "previous.failed".parse::<Predicate>().unwrap(),
+            Predicate::Previous(PredicateField::Failed)
+        );
+    }
+
+    #[test]
+    fn test_parse_complex() {
+        let predicate = "!previous.failed && (steps.compile.status == \"SUCCESS\" || \
+                         steps.run-1.report.exit_code != -1)"
+            .parse::<Predicate>()
+            .unwrap();
+
+        assert_eq!(
+            predicate,
+            Predicate::And(
+                Box::new(Predicate::Not(Box::new(Predicate::Previous(PredicateField::Failed)))),
+                Box::new(Predicate::Or(
+                    Box::new(Predicate::Compare {
+                        left: Box::new(Predicate::Step {
+                            name: "compile".to_owned(),
+                            field: PredicateField::Status,
+                        }),
+                        operator: PredicateOperator::Eq,
+                        right: Box::new(Predicate::Literal(json!("SUCCESS"))),
+                    }),
+                    Box::new(Predicate::Compare {
+                        left: Box::new(Predicate::Step {
+                            name: "run-1".to_owned(),
+                            field: PredicateField::Report(vec!["exit_code".to_owned()]),
+                        }),
+                        operator: PredicateOperator::Ne,
+                        right: Box::new(Predicate::Literal(json!(-1))),
+                    }),
+                ))
+            )
+        );
+        assert_eq!(predicate.steps(), vec!["compile", "run-1"]);
+    }
+
+    #[test]
+    fn test_parse_tags() {
+        assert_eq!(
+            "steps.0.tags.lang == 'cpp'".parse::<Predicate>().unwrap(),
+            Predicate::Compare {
+                left: Box::new(Predicate::Step {
+                    name: "0".to_owned(),
+                    field: PredicateField::Tag("lang".to_owned()),
+                }),
+                operator: PredicateOperator::Eq,
+                right: Box::new(Predicate::Literal(json!("cpp"))),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_outputs() {
+        assert_eq!(
+            "previous.outputs.lang == 'cpp'".parse::<Predicate>().unwrap(),
+            Predicate::Compare {
+                left: Box::new(Predicate::Previous(PredicateField::Output("lang".to_owned()))),
+                operator: PredicateOperator::Eq,
+                right: Box::new(Predicate::Literal(json!("cpp"))),
+            }
+        );
+        assert!("steps.detect.outputs".parse::<Predicate>().is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for source in [
+            "",
+            "previous",
+            "previous.unknown",
+            "foo.bar",
+            "steps",
+            "previous.ok &&",
+            "previous.ok & always",
+            "(previous.ok",
+            "previous.ok always",
+            "steps.compile.status = 'SUCCESS'",
+            "'unterminated",
+        ] {
+            assert!(source.parse::<Predicate>().is_err(), "case {source}");
+        }
+    }
+}
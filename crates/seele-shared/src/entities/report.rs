@@ -1,15 +1,128 @@
 use std::path::PathBuf;
 
 use indexmap::IndexMap;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use url::Url;
 
 pub type SubmissionReport = IndexMap<String, serde_yaml::Value>;
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 #[serde(untagged)]
 pub enum SubmissionReporter {
     JavaScript { javascript: String },
+    Oj { oj: OjReporterConfig },
+    Progress { progress: ProgressReporterConfig },
+}
+
+/// The built-in reporter counting the completed tasks, which is meant for the
+/// progress reports
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProgressReporterConfig {
+    /// The dot-separated name of the task whose subtasks are counted, such as
+    /// the parallel task running the test cases. All the action tasks are
+    /// counted if not specified
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cases: Option<String>,
+
+    /// The dot-separated name of the task running the program in each case,
+    /// only used with `cases`
+    #[serde(default = "default_oj_run")]
+    pub run: String,
+
+    /// The dot-separated name of the task comparing the output in each case,
+    /// only used with `cases`
+    #[serde(default = "default_oj_compare")]
+    pub compare: String,
+}
+
+/// The built-in reporter for the judge tasks of online judges, which runs the
+/// test cases in a parallel task
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct OjReporterConfig {
+    /// The dot-separated name of the compilation task, such as `compile`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compile: Option<String>,
+
+    /// The dot-separated name of the parallel task running the test cases
+    pub cases: String,
+
+    /// The dot-separated name of the task running the program in each case
+    #[serde(default = "default_oj_run")]
+    pub run: String,
+
+    /// The dot-separated name of the task comparing the output in each case
+    #[serde(default = "default_oj_compare")]
+    pub compare: String,
+
+    /// The name of the output of the comparing task holding the fraction of
+    /// the score earned by the case, from `0` to `1`, for the checkers giving
+    /// partial points
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub points: Option<String>,
+
+    #[serde(default)]
+    pub scoring: ScoringConfig,
+}
+
+/// How the cases are scored, either individually by their weights or in
+/// groups as subtasks
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum ScoringConfig {
+    Subtasks {
+        subtasks: Vec<SubtaskConfig>,
+    },
+    Cases {
+        /// The score of passing all the cases
+        #[serde(default = "default_total_score")]
+        total: f64,
+
+        /// The weights of the cases in order, the total score is split evenly
+        /// if not specified
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        weights: Option<Vec<f64>>,
+    },
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        Self::Cases { total: default_total_score(), weights: None }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SubtaskConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// The score of passing all the cases of the subtask
+    pub score: f64,
+
+    /// The indexes of the cases in the subtask
+    pub cases: Vec<usize>,
+
+    #[serde(default)]
+    pub rule: SubtaskRule,
+
+    /// The indexes of the previous subtasks which must be passed to score
+    /// this subtask
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubtaskRule {
+    /// The subtask earns the lowest fraction among its cases
+    #[default]
+    Min,
+
+    /// The subtask earns the average fraction of its cases
+    Sum,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -24,7 +137,7 @@ pub struct SubmissionReportConfig {
     pub uploads: Vec<SubmissionReportUploadConfig>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct SubmissionReportEmbedConfig {
     pub path: PathBuf,
@@ -35,7 +148,7 @@ pub struct SubmissionReportEmbedConfig {
     pub ignore_if_missing: bool,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct SubmissionReportUploadConfig {
     pub path: PathBuf,
@@ -51,13 +164,28 @@ pub struct SubmissionReportUploadConfig {
     pub ignore_if_missing: bool,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum SubmissionReportUploadMethod {
     Post,
     Put,
 }
 
+#[inline]
+fn default_oj_run() -> String {
+    "run".to_owned()
+}
+
+#[inline]
+fn default_oj_compare() -> String {
+    "compare".to_owned()
+}
+
+#[inline]
+fn default_total_score() -> f64 {
+    100.0
+}
+
 #[inline]
 fn default_upload_method() -> SubmissionReportUploadMethod {
     SubmissionReportUploadMethod::Post
@@ -1,27 +1,64 @@
 use std::{
     collections::HashMap,
     path::PathBuf,
-    sync::{Arc, RwLock},
+    slice,
+    sync::{Arc, OnceLock, RwLock},
 };
 
 use chrono::{DateTime, Utc};
 use either::Either;
 use indexmap::IndexMap;
-use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize, Serializer, ser::Error as _};
 
 use super::*;
+use crate::priority::Priority;
 
 pub type UtcTimestamp = DateTime<Utc>;
 
 pub type SequenceTasks = IndexMap<String, Arc<TaskConfig>>;
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 #[serde(untagged)]
 pub enum ParallelTasks {
     Anonymous(Vec<Arc<TaskConfig>>),
+    Matrix(MatrixTasks),
     Named(IndexMap<String, Arc<TaskConfig>>),
 }
 
+/// Parallel tasks generated from a template for each combination of the matrix
+/// values, which are expanded into named tasks when resolving the submission
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MatrixTasks {
+    #[schemars(with = "IndexMap<String, Vec<serde_json::Value>>")]
+    pub matrix: IndexMap<String, Vec<serde_yaml::Value>>,
+
+    #[schemars(with = "TaskConfig")]
+    pub task: serde_yaml::Value,
+
+    #[serde(skip)]
+    pub tasks: OnceLock<IndexMap<String, Arc<TaskConfig>>>,
+}
+
+impl MatrixTasks {
+    /// The generated tasks, which are only available once the submission is
+    /// resolved
+    #[inline]
+    pub fn expanded(&self) -> Option<&IndexMap<String, Arc<TaskConfig>>> {
+        self.tasks.get()
+    }
+}
+
+impl Serialize for MatrixTasks {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.expanded() {
+            Some(tasks) => tasks.serialize(serializer),
+            None => Err(S::Error::custom("The matrix tasks have not been expanded")),
+        }
+    }
+}
+
 impl<'a> ParallelTasks {
     pub fn iter(
         &'a self,
@@ -29,30 +66,52 @@ impl<'a> ParallelTasks {
         impl Iterator<Item = Arc<TaskConfig>> + 'a,
         impl Iterator<Item = Arc<TaskConfig>> + 'a,
     > {
-        match self {
-            Self::Anonymous(tasks) => Either::Left(tasks.iter().cloned()),
-            Self::Named(tasks) => Either::Right(tasks.values().cloned()),
-        }
+        let tasks = match self {
+            Self::Anonymous(tasks) => return Either::Left(tasks.iter().cloned()),
+            Self::Named(tasks) => Some(tasks),
+            Self::Matrix(tasks) => tasks.expanded(),
+        };
+        Either::Right(tasks.into_iter().flat_map(|tasks| tasks.values().cloned()))
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct SubmissionConfig {
     #[serde(skip_serializing, skip_deserializing, default = "make_submitted_at")]
     pub submitted_at: UtcTimestamp,
 
+    // The default value is random, keep it out of the schema
     #[serde(default = "random_submission_id")]
+    #[schemars(skip_serializing)]
     pub id: String,
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub tracing_attribute: Option<String>,
 
+    #[serde(default, skip_serializing)]
+    pub timeout_ms: Option<u64>,
+
+    /// The greater one is scheduled first, falls back to the priority of the
+    /// message delivering the submission
+    #[serde(default, skip_serializing)]
+    pub priority: Option<Priority>,
+
+    /// Values referenced as `${{ vars.<name> }}` in the steps, which are
+    /// interpolated before the config is deserialized
+    #[serde(default, skip_serializing)]
+    #[schemars(with = "IndexMap<String, serde_json::Value>")]
+    pub vars: IndexMap<String, serde_yaml::Value>,
+
     #[serde(rename = "steps")]
     pub tasks: SequenceTasks,
 
     #[serde(default, skip_serializing)]
     pub reporter: Option<SubmissionReporter>,
+
+    /// The reporter of the progress reports, falls back to `reporter`
+    #[serde(default, skip_serializing)]
+    pub progress_reporter: Option<SubmissionReporter>,
 }
 
 #[inline]
@@ -68,12 +127,16 @@ fn random_submission_id() -> String {
 #[derive(Debug, Clone, Serialize)]
 pub struct Submission {
     pub id: String,
+
+    #[serde(skip)]
+    pub priority: Priority,
+
     pub root_directory: PathBuf,
     pub config: Arc<SubmissionConfig>,
     pub root_node: Arc<RootTaskNode>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 pub struct TaskConfig {
     #[serde(default, skip_serializing)]
     pub progress: bool,
@@ -81,26 +144,59 @@ pub struct TaskConfig {
     #[serde(default, skip_serializing)]
     pub report: Option<TaskReportConfig>,
 
+    #[serde(default, skip_serializing)]
+    pub retry: Option<TaskRetryConfig>,
+
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub tags: Option<HashMap<String, String>>,
 
     #[serde(default, flatten, skip_deserializing)]
+    #[schemars(skip)]
     pub status: RwLock<TaskStatus>,
 
     #[serde(default, skip_deserializing)]
+    #[schemars(skip)]
     pub embeds: RwLock<TaskEmbeds>,
 
+    #[serde(default, skip_serializing)]
+    pub outputs: IndexMap<String, TaskOutputConfig>,
+
+    #[serde(
+        rename = "outputs",
+        default,
+        skip_deserializing,
+        skip_serializing_if = "TaskOutputs::is_empty"
+    )]
+    #[schemars(skip)]
+    pub output_values: RwLock<TaskOutputs>,
+
     #[serde(default, skip_serializing)]
     pub when: Option<String>,
 
     #[serde(default, skip_serializing)]
-    pub needs: Option<String>,
+    pub needs: Option<TaskNeeds>,
 
     #[serde(skip_serializing_if = "TaskConfigExt::is_action_task", flatten)]
     pub ext: TaskConfigExt,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(untagged)]
+pub enum TaskNeeds {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl TaskNeeds {
+    pub fn names(&self) -> &[String] {
+        match self {
+            Self::Single(name) => slice::from_ref(name),
+            Self::Multiple(names) => names,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 pub struct TaskReportConfig {
     #[serde(default)]
     pub embeds: Vec<TaskReportEmbedConfig>,
@@ -109,7 +205,47 @@ pub struct TaskReportConfig {
     pub uploads: Vec<TaskReportUploadConfig>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaskRetryConfig {
+    /// The maximum number of attempts, including the first one
+    pub max_attempts: usize,
+
+    #[serde(default)]
+    pub backoff_ms: u64,
+
+    #[serde(default = "default_backoff_factor")]
+    pub backoff_factor: f64,
+
+    /// The upper bound of the delay between the attempts
+    #[serde(default = "default_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+
+    /// Whether to retry when the action fails with an internal error
+    #[serde(default = "default_retry_internal_error")]
+    pub internal_error: bool,
+
+    /// The container execution statuses to retry on
+    #[serde(default)]
+    pub statuses: Vec<run_container::ExecutionStatus>,
+}
+
+#[inline]
+fn default_backoff_factor() -> f64 {
+    1.0
+}
+
+#[inline]
+fn default_max_backoff_ms() -> u64 {
+    60_000
+}
+
+#[inline]
+fn default_retry_internal_error() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 pub struct TaskReportEmbedConfig {
     #[serde(default)]
     pub when: TaskReportWhenConfig,
@@ -118,7 +254,7 @@ pub struct TaskReportEmbedConfig {
     pub inner: SubmissionReportEmbedConfig,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 pub struct TaskReportUploadConfig {
     #[serde(default)]
     pub when: TaskReportWhenConfig,
@@ -127,17 +263,61 @@ pub struct TaskReportUploadConfig {
     pub inner: SubmissionReportUploadConfig,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Default, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum TaskReportWhenConfig {
     Success,
     Failure,
+    #[default]
     Always,
 }
 
-impl Default for TaskReportWhenConfig {
+/// Where the value of an output is read from after the action completes
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(untagged)]
+pub enum TaskOutputConfig {
+    /// The content of a file in the submission root, with the trailing
+    /// whitespaces trimmed
+    File { file: PathBuf },
+
+    /// A field of a JSON file in the submission root, such as the redirected
+    /// stdout of a container. The whole document is read if `field` is empty.
+    Json {
+        json: PathBuf,
+
+        #[serde(default)]
+        field: String,
+    },
+
+    /// A field of the action's report, such as `exit_code`
+    Report { report: String },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum TaskOutputs {
+    Error(String),
+    Values(IndexMap<String, serde_json::Value>),
+}
+
+impl TaskOutputs {
+    fn is_empty(outputs: &RwLock<Self>) -> bool {
+        matches!(&*outputs.read().unwrap(), Self::Values(values) if values.is_empty())
+    }
+
+    /// Returns the value of the output, or `None` if it is missing or the
+    /// outputs failed to be collected
+    pub fn get(&self, name: &str) -> Option<&serde_json::Value> {
+        match self {
+            Self::Error(_) => None,
+            Self::Values(values) => values.get(name),
+        }
+    }
+}
+
+impl Default for TaskOutputs {
     fn default() -> Self {
-        Self::Always
+        Self::Values(Default::default())
     }
 }
 
@@ -154,7 +334,7 @@ impl Default for TaskEmbeds {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 #[serde(untagged)]
 #[allow(clippy::large_enum_variant)]
 pub enum TaskConfigExt {
@@ -170,32 +350,39 @@ impl TaskConfigExt {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct SequenceTaskConfig {
     #[serde(rename = "steps")]
     pub tasks: SequenceTasks,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct ParallelTaskConfig {
     #[serde(rename = "parallel")]
     pub tasks: ParallelTasks,
+
+    #[serde(default, skip_serializing)]
+    pub max_concurrency: Option<usize>,
+
+    #[serde(default, skip_serializing)]
+    pub fail_fast: bool,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Default, Serialize)]
 #[serde(tag = "status", rename_all = "UPPERCASE")]
 pub enum TaskStatus {
+    #[default]
     Pending,
     Skipped,
     Running,
-    Failed { report: TaskFailedReport },
-    Success { report: TaskSuccessReport },
-}
-
-impl Default for TaskStatus {
-    fn default() -> Self {
-        Self::Pending
-    }
+    Timeout,
+    Cancelled,
+    Failed {
+        report: TaskFailedReport,
+    },
+    Success {
+        report: TaskSuccessReport,
+    },
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -235,15 +422,26 @@ impl From<ActionReport> for TaskStatus {
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ActionSuccessReport {
     pub run_at: UtcTimestamp,
     pub time_elapsed_ms: u64,
 
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub previous_attempts: Vec<ActionAttemptReport>,
+
     #[serde(flatten)]
     pub ext: ActionSuccessReportExt,
 }
 
+/// A failed attempt of an action which has been retried
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ActionAttemptReport {
+    Error { error: String },
+    Failed(ActionFailedReport),
+}
+
 #[derive(Debug, Clone, Serialize)]
 #[serde(untagged)]
 pub enum TaskFailedReport {
@@ -263,13 +461,19 @@ pub struct ParallelFailedReport {
     pub time_elapsed_ms: u64,
     pub failed_count: usize,
     pub failed_indexes: Vec<usize>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skipped_indexes: Vec<usize>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ActionFailedReport {
     pub run_at: UtcTimestamp,
     pub time_elapsed_ms: u64,
 
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub previous_attempts: Vec<ActionAttemptReport>,
+
     #[serde(flatten)]
     pub ext: ActionFailureReportExt,
 }
@@ -284,6 +488,14 @@ pub struct TaskNode {
     pub name: String,
     #[serde(skip_serializing)]
     pub config: Arc<TaskConfig>,
+    #[serde(skip_serializing)]
+    pub when: Predicate,
+    /// The tasks in the same sequence referenced by `steps.<name>` in the
+    /// `when` field or the placeholders of the action config
+    #[serde(skip_serializing)]
+    pub steps: HashMap<String, Arc<TaskConfig>>,
+    #[serde(skip_serializing)]
+    pub parents: Vec<Arc<TaskConfig>>,
     pub children: Vec<Arc<TaskNode>>,
     pub ext: TaskNodeExt,
 }
@@ -1,6 +1,9 @@
-use std::path::Path;
+use std::{
+    io::{self, ErrorKind},
+    path::{Component, Path, PathBuf},
+};
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use tokio::fs;
 
 pub async fn create_parent_directories(path: &Path) -> Result<()> {
@@ -15,3 +18,123 @@ pub async fn create_file(path: &Path) -> Result<fs::File> {
     create_parent_directories(path).await?;
     fs::File::create(path).await.context("Error creating the file")
 }
+
+/// Resolves the path relative to the root, which must stay inside the root
+/// after following the symbolic links, so the paths from the submissions cannot
+/// reach the files of the host. The path may not exist yet.
+pub fn resolve_path(root: &Path, path: impl AsRef<Path>) -> Result<PathBuf> {
+    let path = path.as_ref();
+    if path
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        bail!("The path must be relative to the submission root without `..`: {}", path.display());
+    }
+
+    let root = root.canonicalize().context("Error resolving the submission root")?;
+    let resolved = canonicalize_existing(&root.join(path))
+        .with_context(|| format!("Error resolving the path: {}", path.display()))?;
+    if !resolved.starts_with(&root) {
+        bail!("The path escapes from the submission root: {}", path.display());
+    }
+
+    Ok(resolved)
+}
+
+/// Resolves the host path, which must be inside one of the allowed paths after
+/// following the symbolic links
+pub fn resolve_allowed_path(path: &Path, allowed_paths: &[PathBuf]) -> Result<PathBuf> {
+    let resolved = canonicalize_existing(path)
+        .with_context(|| format!("Error resolving the host path: {}", path.display()))?;
+    if !allowed_paths
+        .iter()
+        .filter_map(|allowed| allowed.canonicalize().ok())
+        .any(|allowed| resolved.starts_with(allowed))
+    {
+        bail!("The host path is not in the allowlist: {}", path.display());
+    }
+
+    Ok(resolved)
+}
+
+/// Canonicalizes the deepest existing ancestor of the path, and appends the
+/// missing components to it. A dangling symbolic link is rejected, since it
+/// would be followed when the file is created.
+pub fn canonicalize_existing(path: &Path) -> io::Result<PathBuf> {
+    let mut missing = vec![];
+    let mut current = path;
+    loop {
+        match current.canonicalize() {
+            Ok(resolved) => {
+                return Ok(missing.into_iter().rev().fold(resolved, |path, name| path.join(name)));
+            }
+            Err(err)
+                if err.kind() == ErrorKind::NotFound && current.symlink_metadata().is_err() =>
+            {
+                let (Some(parent), Some(name)) = (current.parent(), current.file_name()) else {
+                    return Err(err);
+                };
+                missing.push(name);
+                current = parent;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, os::unix::fs::symlink};
+
+    #[test]
+    fn test_resolve_path() {
+        let root = env::temp_dir().join(format!("seele-file-{:x}", rand::random::<u64>()));
+        fs::create_dir_all(root.join("nested")).unwrap();
+        fs::write(root.join("nested/out.txt"), "114514").unwrap();
+        symlink("/etc", root.join("etc")).unwrap();
+        symlink("/etc/seele-missing", root.join("dangling")).unwrap();
+        symlink("nested", root.join("inner")).unwrap();
+
+        let canonical_root = root.canonicalize().unwrap();
+        for (path, resolved) in [
+            ("nested/out.txt", "nested/out.txt"),
+            ("./nested/new/file.txt", "nested/new/file.txt"),
+            ("inner/out.txt", "nested/out.txt"),
+        ] {
+            assert_eq!(
+                super::resolve_path(&root, path).unwrap(),
+                canonical_root.join(resolved),
+                "case {path}"
+            );
+        }
+
+        for path in
+            ["/etc/passwd", "../out.txt", "nested/../out.txt", "etc/passwd", "etc/new", "dangling"]
+        {
+            assert!(super::resolve_path(&root, path).is_err(), "case {path}");
+        }
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_allowed_path() {
+        let root = env::temp_dir().join(format!("seele-file-{:x}", rand::random::<u64>()));
+        fs::create_dir_all(root.join("allowed")).unwrap();
+        fs::write(root.join("allowed/data.txt"), "114514").unwrap();
+        fs::write(root.join("secret.txt"), "1919810").unwrap();
+        symlink("../secret.txt", root.join("allowed/secret.txt")).unwrap();
+
+        let allowlist = [root.join("allowed")];
+        assert_eq!(
+            super::resolve_allowed_path(&root.join("allowed/data.txt"), &allowlist).unwrap(),
+            root.canonicalize().unwrap().join("allowed/data.txt")
+        );
+        for path in ["secret.txt", "allowed/secret.txt", "allowed/../secret.txt"] {
+            assert!(super::resolve_allowed_path(&root.join(path), &allowlist).is_err(), "{path}");
+        }
+        assert!(super::resolve_allowed_path(&root.join("allowed/data.txt"), &[]).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
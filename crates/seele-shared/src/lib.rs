@@ -12,12 +12,16 @@ pub mod entities;
 pub mod file;
 pub mod http;
 pub mod metrics;
+pub mod priority;
 pub mod runner;
 
 pub static TINI_PRESENTS: LazyLock<bool> = LazyLock::new(|| env::var_os("TINI_VERSION").is_some());
 
 pub static ABORTED_MESSAGE: &str = "Aborted due to shutting down";
 
+pub static SUBMISSION_ABORTED_MESSAGE: &str =
+    "Aborted due to the submission reaching its deadline or being cancelled";
+
 pub async fn tail(file: File, count: u64) -> Result<Vec<u8>> {
     let metadata = file.metadata().await?;
     let mut reader = BufReader::new(file);
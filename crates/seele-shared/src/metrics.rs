@@ -9,7 +9,7 @@ use opentelemetry::{
 };
 use opentelemetry_sdk::{Resource, metrics::SdkMeterProvider};
 
-use super::runner;
+use super::{priority, runner};
 use crate::conf;
 
 static METRICS_RESOURCE: LazyLock<Resource> = LazyLock::new(|| {
@@ -108,6 +108,19 @@ fn register_observable_gauges() {
             observer.observe(runner::PENDING_TASKS.load(Ordering::SeqCst), &[])
         })
         .build();
+
+    SEELE_METER
+        .u64_observable_gauge("seele.queue.depth")
+        .with_description("Count of pending items in the scheduling queues of each priority")
+        .with_callback(|observer| {
+            for (name, priority, depth) in priority::queue_depths() {
+                observer.observe(depth as u64, &[
+                    KeyValue::new("seele.queue.name", name),
+                    KeyValue::new("seele.queue.priority", i64::from(priority)),
+                ]);
+            }
+        })
+        .build();
 }
 
 static SUBMISSION_HANDLING_HISTOGRAM: LazyLock<Histogram<f64>> = LazyLock::new(|| {
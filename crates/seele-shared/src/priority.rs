@@ -0,0 +1,408 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, VecDeque},
+    sync::{
+        Arc, LazyLock, Mutex, Weak,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use anyhow::{Result, bail};
+use tokio::{
+    sync::{Notify, oneshot},
+    time::Instant,
+};
+
+/// The priority of a submission, the greater one is scheduled first
+pub type Priority = u8;
+
+pub const DEFAULT_PRIORITY: Priority = 0;
+
+type NamedQueue = (&'static str, Weak<dyn QueueDepths>);
+
+static QUEUES: LazyLock<Mutex<Vec<NamedQueue>>> = LazyLock::new(Default::default);
+
+trait QueueDepths: Send + Sync {
+    fn depths(&self) -> Vec<(Priority, usize)>;
+}
+
+fn register_queue(name: &'static str, queue: Weak<dyn QueueDepths>) {
+    let mut queues = QUEUES.lock().unwrap();
+    queues.retain(|(_, queue)| queue.strong_count() > 0);
+    queues.push((name, queue));
+}
+
+/// Count of the pending items of each priority in every living queue, keyed by
+/// the name of the queue
+pub fn queue_depths() -> Vec<(&'static str, Priority, usize)> {
+    let queues = QUEUES.lock().unwrap();
+    queues
+        .iter()
+        .filter_map(|(name, queue)| Some((*name, queue.upgrade()?)))
+        .flat_map(|(name, queue)| {
+            queue.depths().into_iter().map(move |(priority, depth)| (name, priority, depth))
+        })
+        .collect()
+}
+
+/// Pending items grouped by their priorities, the items of the same priority
+/// are popped in FIFO order. The effective priority of an item grows by one
+/// for each aging interval it has waited, so the items of low priorities are
+/// never starved. A zero aging interval disables the aging.
+#[derive(Debug)]
+pub struct PriorityQueue<T> {
+    aging_interval: Duration,
+    groups: BTreeMap<Priority, VecDeque<(Instant, T)>>,
+}
+
+impl<T> PriorityQueue<T> {
+    pub fn new(aging_interval: Duration) -> Self {
+        Self { aging_interval, groups: BTreeMap::new() }
+    }
+
+    pub fn push(&mut self, priority: Priority, item: T) {
+        self.groups.entry(priority).or_default().push_back((Instant::now(), item));
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        let now = Instant::now();
+        let (priority, ..) = self
+            .groups
+            .iter()
+            .filter_map(|(&priority, items)| {
+                let &(enqueued_at, _) = items.front()?;
+                Some((priority, self.effective_priority(priority, now - enqueued_at), enqueued_at))
+            })
+            .max_by_key(|&(_, effective_priority, enqueued_at)| {
+                (effective_priority, Reverse(enqueued_at))
+            })?;
+
+        self.groups.get_mut(&priority)?.pop_front().map(|(_, item)| item)
+    }
+
+    pub fn retain(&mut self, mut f: impl FnMut(&T) -> bool) {
+        for items in self.groups.values_mut() {
+            items.retain(|(_, item)| f(item));
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.groups.values().map(VecDeque::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.values().all(VecDeque::is_empty)
+    }
+
+    /// Count of the pending items of each priority ever pushed, in ascending
+    /// order of the priorities
+    pub fn depths(&self) -> Vec<(Priority, usize)> {
+        self.groups.iter().map(|(&priority, items)| (priority, items.len())).collect()
+    }
+
+    fn effective_priority(&self, priority: Priority, waited: Duration) -> u128 {
+        let aged = waited.as_nanos().checked_div(self.aging_interval.as_nanos()).unwrap_or(0);
+        u128::from(priority) + aged
+    }
+}
+
+struct Channel<T> {
+    queue: Mutex<PriorityQueue<T>>,
+    notify: Notify,
+    senders: AtomicUsize,
+    closed: AtomicBool,
+}
+
+impl<T: Send> QueueDepths for Channel<T> {
+    fn depths(&self) -> Vec<(Priority, usize)> {
+        self.queue.lock().unwrap().depths()
+    }
+}
+
+/// Creates an unbounded channel whose items are received by their priorities,
+/// see [`PriorityQueue`]
+pub fn channel<T: Send + 'static>(
+    name: &'static str,
+    aging_interval: Duration,
+) -> (PrioritySender<T>, PriorityReceiver<T>) {
+    let channel = Arc::new(Channel {
+        queue: Mutex::new(PriorityQueue::new(aging_interval)),
+        notify: Notify::new(),
+        senders: AtomicUsize::new(1),
+        closed: AtomicBool::new(false),
+    });
+    register_queue(name, Arc::downgrade(&channel) as Weak<dyn QueueDepths>);
+
+    (PrioritySender { channel: channel.clone() }, PriorityReceiver { channel })
+}
+
+pub struct PrioritySender<T> {
+    channel: Arc<Channel<T>>,
+}
+
+impl<T> PrioritySender<T> {
+    pub fn send(&self, priority: Priority, item: T) -> Result<()> {
+        if self.channel.closed.load(Ordering::SeqCst) {
+            bail!("The receiver has been dropped");
+        }
+
+        self.channel.queue.lock().unwrap().push(priority, item);
+        self.channel.notify.notify_one();
+        Ok(())
+    }
+}
+
+impl<T> Clone for PrioritySender<T> {
+    fn clone(&self) -> Self {
+        self.channel.senders.fetch_add(1, Ordering::SeqCst);
+        Self { channel: self.channel.clone() }
+    }
+}
+
+impl<T> Drop for PrioritySender<T> {
+    fn drop(&mut self) {
+        if self.channel.senders.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.channel.notify.notify_one();
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for PrioritySender<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PrioritySender").finish_non_exhaustive()
+    }
+}
+
+pub struct PriorityReceiver<T> {
+    channel: Arc<Channel<T>>,
+}
+
+impl<T> PriorityReceiver<T> {
+    /// Receives the item of the highest effective priority, returns `None` if
+    /// the channel is empty and all the senders have been dropped
+    pub async fn recv(&mut self) -> Option<T> {
+        loop {
+            let notified = self.channel.notify.notified();
+            {
+                let mut queue = self.channel.queue.lock().unwrap();
+                if let Some(item) = queue.pop() {
+                    return Some(item);
+                }
+                if self.channel.senders.load(Ordering::SeqCst) == 0 {
+                    return None;
+                }
+            }
+            notified.await;
+        }
+    }
+
+    pub fn try_recv(&mut self) -> Option<T> {
+        self.channel.queue.lock().unwrap().pop()
+    }
+}
+
+impl<T> Drop for PriorityReceiver<T> {
+    fn drop(&mut self) {
+        self.channel.closed.store(true, Ordering::SeqCst);
+    }
+}
+
+impl<T> std::fmt::Debug for PriorityReceiver<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PriorityReceiver").finish_non_exhaustive()
+    }
+}
+
+struct SemaphoreState {
+    permits: usize,
+    waiters: PriorityQueue<oneshot::Sender<()>>,
+}
+
+impl QueueDepths for Mutex<SemaphoreState> {
+    fn depths(&self) -> Vec<(Priority, usize)> {
+        self.lock().unwrap().waiters.depths()
+    }
+}
+
+/// A semaphore which grants the permits to the waiters by their priorities,
+/// see [`PriorityQueue`]
+pub struct PrioritySemaphore {
+    state: Arc<Mutex<SemaphoreState>>,
+}
+
+impl PrioritySemaphore {
+    pub fn new(name: &'static str, permits: usize, aging_interval: Duration) -> Self {
+        let state = Arc::new(Mutex::new(SemaphoreState {
+            permits,
+            waiters: PriorityQueue::new(aging_interval),
+        }));
+        register_queue(name, Arc::downgrade(&state) as Weak<dyn QueueDepths>);
+
+        Self { state }
+    }
+
+    pub async fn acquire(&self, priority: Priority) -> PrioritySemaphorePermit<'_> {
+        let rx = {
+            let mut state = self.state.lock().unwrap();
+            if state.permits > 0 && state.waiters.is_empty() {
+                state.permits -= 1;
+                return PrioritySemaphorePermit { semaphore: self };
+            }
+
+            let (tx, rx) = oneshot::channel();
+            state.waiters.push(priority, tx);
+            rx
+        };
+
+        let mut waiter = Waiter { semaphore: self, rx, granted: false };
+        (&mut waiter.rx).await.expect("The waiter should not be dropped");
+        waiter.granted = true;
+        PrioritySemaphorePermit { semaphore: self }
+    }
+
+    fn release(&self) {
+        let mut state = self.state.lock().unwrap();
+        while let Some(tx) = state.waiters.pop() {
+            if tx.send(()).is_ok() {
+                return;
+            }
+        }
+        state.permits += 1;
+    }
+}
+
+/// Hands the permit over if the acquiring future is dropped after being
+/// granted
+struct Waiter<'a> {
+    semaphore: &'a PrioritySemaphore,
+    rx: oneshot::Receiver<()>,
+    granted: bool,
+}
+
+impl Drop for Waiter<'_> {
+    fn drop(&mut self) {
+        if self.granted {
+            return;
+        }
+
+        self.rx.close();
+        if self.rx.try_recv().is_ok() {
+            self.semaphore.release();
+        } else {
+            self.semaphore.state.lock().unwrap().waiters.retain(|tx| !tx.is_closed());
+        }
+    }
+}
+
+pub struct PrioritySemaphorePermit<'a> {
+    semaphore: &'a PrioritySemaphore,
+}
+
+impl Drop for PrioritySemaphorePermit<'_> {
+    fn drop(&mut self) {
+        self.semaphore.release();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use tokio::time::{self, sleep};
+
+    use super::{PriorityQueue, PrioritySemaphore};
+
+    #[tokio::test(start_paused = true)]
+    async fn test_priority_queue() {
+        let mut queue = PriorityQueue::new(Duration::from_secs(10));
+        queue.push(0, "rejudge-1");
+        queue.push(0, "rejudge-2");
+        queue.push(5, "contest-1");
+        queue.push(5, "contest-2");
+        assert_eq!(queue.pop(), Some("contest-1"));
+        assert_eq!(queue.depths(), vec![(0, 2), (5, 1)]);
+
+        // The rejudges have waited long enough to overtake the new submissions
+        time::advance(Duration::from_secs(60)).await;
+        queue.push(5, "contest-3");
+        assert_eq!(queue.pop(), Some("contest-2"));
+        assert_eq!(queue.pop(), Some("rejudge-1"));
+        assert_eq!(queue.pop(), Some("rejudge-2"));
+        assert_eq!(queue.pop(), Some("contest-3"));
+        assert_eq!(queue.pop(), None);
+        assert!(queue.is_empty());
+
+        let mut queue = PriorityQueue::new(Duration::ZERO);
+        queue.push(0, "rejudge");
+        time::advance(Duration::from_secs(3600)).await;
+        queue.push(1, "contest");
+        assert_eq!(queue.pop(), Some("contest"));
+    }
+
+    #[tokio::test]
+    async fn test_priority_channel() {
+        let (tx, mut rx) = super::channel("test", Duration::from_secs(10));
+        tx.send(0, 1).unwrap();
+        tx.send(9, 2).unwrap();
+        tx.send(3, 3).unwrap();
+
+        let other_tx = tx.clone();
+        drop(tx);
+        other_tx.send(0, 4).unwrap();
+        drop(other_tx);
+
+        let mut items = vec![];
+        while let Some(item) = rx.recv().await {
+            items.push(item);
+        }
+        assert_eq!(items, vec![2, 3, 1, 4]);
+
+        let (tx, rx) = super::channel::<()>("test", Duration::from_secs(10));
+        drop(rx);
+        assert!(tx.send(0, ()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_priority_semaphore() {
+        let semaphore = Arc::new(PrioritySemaphore::new("test", 1, Duration::from_secs(10)));
+        let permit = semaphore.acquire(0).await;
+
+        let (order_tx, mut order_rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut handles = vec![];
+        for priority in [1, 5, 3] {
+            let semaphore = semaphore.clone();
+            let order_tx = order_tx.clone();
+            handles.push(tokio::spawn(async move {
+                let _permit = semaphore.acquire(priority).await;
+                order_tx.send(priority).unwrap();
+            }));
+            sleep(Duration::from_millis(10)).await;
+        }
+
+        // An aborted waiter neither blocks the others nor takes the permit
+        let aborted = tokio::spawn({
+            let semaphore = semaphore.clone();
+            async move {
+                let _permit = semaphore.acquire(9).await;
+                unreachable!();
+            }
+        });
+        sleep(Duration::from_millis(10)).await;
+        aborted.abort();
+        _ = aborted.await;
+        assert_eq!(semaphore.state.lock().unwrap().waiters.len(), 3);
+
+        drop(permit);
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        drop(order_tx);
+
+        let mut order = vec![];
+        while let Some(priority) = order_rx.recv().await {
+            order.push(priority);
+        }
+        assert_eq!(order, vec![5, 3, 1]);
+    }
+}
@@ -8,9 +8,10 @@ use bytes::Bytes;
 use futures_util::{Stream, StreamExt, future};
 use http_cache::HttpCacheOptions;
 use seele_shared::entities::add_file::*;
+use sha2::{Digest, Sha256};
 use tokio::{
     fs::File,
-    io::{self, AsyncWriteExt},
+    io::{self, AsyncReadExt, AsyncWriteExt},
     task::spawn_blocking,
 };
 use tracing::{info, instrument};
@@ -33,7 +34,7 @@ pub async fn execute(
         let handle = handle.clone();
         async move {
             let file = {
-                let path: PathBuf = ctx.submission_root.join(&item.path);
+                let path = shared::file::resolve_path(&ctx.submission_root, &item.path)?;
                 shared::file::create_file(&path).await.context("Error creating the file")?
             };
 
@@ -41,7 +42,9 @@ pub async fn execute(
                 FileItemExt::PlainText { plain } => handle_plain_text(file, plain).await,
                 FileItemExt::Http { url } => handle_http_url(handle, file, url).await,
                 FileItemExt::Base64 { base64 } => handle_base64(file, base64).await,
-                FileItemExt::LocalPath { local } => handle_local_path(file, local).await,
+                FileItemExt::LocalPath { local } => {
+                    handle_local_path(file, &resolve_local_path(local)?).await
+                }
             }
         }
     }))
@@ -89,6 +92,58 @@ async fn handle_local_path(mut file: File, path: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Resolves the local source, which must be in the allowlist so the
+/// submissions cannot read arbitrary files of the host
+fn resolve_local_path(path: &Path) -> Result<PathBuf> {
+    shared::file::resolve_allowed_path(path, &conf::CONFIG.worker.action.add_file.local_allowlist)
+        .context("Error resolving the local path")
+}
+
+/// Computes the digest of the file content fetched from outside the
+/// submission, returns `None` if the content is inlined in the config
+pub async fn digest_file_item(item: &FileItemExt) -> Result<Option<Box<[u8]>>> {
+    let mut hasher = Sha256::new();
+    match item {
+        FileItemExt::PlainText { .. } | FileItemExt::Base64 { .. } => return Ok(None),
+        FileItemExt::Http { url } => match http_entity_tag(url).await {
+            // A strong entity tag identifies the content without downloading it
+            Some(tag) => {
+                hasher.update(url.as_bytes());
+                hasher.update(tag.as_bytes());
+            }
+            None => {
+                let mut stream =
+                    download_http_file(url).await.context("Error downloading the file")?;
+                while let Some(data) = stream.next().await {
+                    hasher.update(data.context("Error reading the remote data")?);
+                }
+            }
+        },
+        FileItemExt::LocalPath { local } => {
+            let mut file =
+                File::open(resolve_local_path(local)?).await.context("Error opening the file")?;
+            let mut buffer = vec![0; 64 * 1024];
+            loop {
+                let count = file.read(&mut buffer).await.context("Error reading the file")?;
+                if count == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..count]);
+            }
+        }
+    }
+
+    Ok(Some(hasher.finalize().to_vec().into_boxed_slice()))
+}
+
+/// Requests the strong entity tag of the remote file, the weak ones only
+/// indicate equivalent contents
+async fn http_entity_tag(url: &str) -> Option<String> {
+    let response = HTTP_CLIENT.head(url).send().await.ok()?.error_for_status().ok()?;
+    let tag = response.headers().get(reqwest::header::ETAG)?.to_str().ok()?;
+    (!tag.starts_with("W/")).then(|| tag.to_owned())
+}
+
 static HTTP_CLIENT: LazyLock<reqwest_middleware::ClientWithMiddleware> = LazyLock::new(|| {
     use std::time::Duration;
 
@@ -1,5 +1,7 @@
 use std::path::PathBuf;
 
+use seele_shared::priority::Priority;
+
 pub mod add_file;
 pub mod noop;
 pub mod run_container;
@@ -7,4 +9,5 @@ pub mod run_container;
 #[derive(Debug)]
 pub struct ActionContext {
     pub submission_root: PathBuf,
+    pub priority: Priority,
 }
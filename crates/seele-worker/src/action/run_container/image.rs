@@ -24,9 +24,13 @@ use triggered::Listener;
 
 use crate::{
     conf,
-    shared::{self, cond::CondGroup, runner},
+    shared::{self, cond::CondGroup, priority::Priority, runner},
 };
 
+/// The preparation of an image is shared by all the submissions waiting for it,
+/// so it goes before any of them
+const IMAGE_PRIORITY: Priority = Priority::MAX;
+
 static PREPARATION_TASKS: LazyLock<CondGroup<OciImage, Result<(), String>>> = LazyLock::new(|| {
     CondGroup::new(|payload: &OciImage| prepare_image_impl(payload.clone()).boxed())
 });
@@ -39,6 +43,12 @@ pub async fn prepare_image(abort: Listener, image: OciImage) -> Result<()> {
     }
 }
 
+/// Reads the index of the pulled image, which identifies the content of the
+/// image, returns `None` if the image has not been pulled yet
+pub async fn read_image_index(image: &OciImage) -> Option<Vec<u8>> {
+    fs::read(get_oci_image_path(image).join("index.json")).await.ok()
+}
+
 #[instrument]
 async fn prepare_image_impl(image: OciImage) -> Result<(), String> {
     pull_image(&image).await.map_err(|err| format!("Error pulling the image: {err:#}"))?;
@@ -76,7 +86,7 @@ async fn pull_image(image: &OciImage) -> Result<()> {
     );
 
     info!(path = %temp_target_path.display(), skopeo = conf::CONFIG.paths.skopeo, "Pulling the image using skopeo");
-    let success = runner::spawn_blocking({
+    let success = runner::spawn_blocking(IMAGE_PRIORITY, {
         let image = image.clone();
         let temp_target_path = temp_target_path.clone();
         let skopeo_log_file_path = skopeo_log_file_path.clone();
@@ -170,7 +180,7 @@ async fn unpack_image(image: &OciImage) -> Result<()> {
         make_timeout_killer(conf::CONFIG.worker.action.run_container.unpack_image_timeout_seconds);
 
     info!(path = %temp_unpacked_path.display(), umoci = conf::CONFIG.paths.umoci, "Unpacking the image using umoci");
-    let success = runner::spawn_blocking({
+    let success = runner::spawn_blocking(IMAGE_PRIORITY, {
         let image = image.clone();
         let image_path = get_oci_image_path(&image);
         let temp_unpacked_path = temp_unpacked_path.clone();
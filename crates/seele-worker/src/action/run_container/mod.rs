@@ -19,7 +19,10 @@ use tracing::{Span, info, info_span, warn};
 use triggered::Listener;
 
 use self::utils::{check_and_create_directories, cleanup_overlayfs, make_runj_config};
-pub use self::{idmap::*, image::prepare_image};
+pub use self::{
+    idmap::*,
+    image::{prepare_image, read_image_index},
+};
 use super::ActionContext;
 use crate::{
     cgroup, conf,
@@ -48,7 +51,7 @@ pub async fn execute(
         make_runj_config(ctx, config.clone()).await.context("Error converting the config")?;
     check_and_create_directories(&runj_config).await?;
 
-    let report = runner::spawn_blocking({
+    let report = runner::spawn_blocking(ctx.priority, {
         let local = RUNNER_THREAD_LOCAL.clone();
         let span = info_span!(
             parent: Span::current(),
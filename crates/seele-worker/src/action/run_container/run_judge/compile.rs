@@ -6,7 +6,6 @@ use anyhow::{Context, Result, bail};
 use seele_shared::entities::{
     ActionReportExt, ActionSuccessReportExt,
     run_container::{
-        self,
         run_judge::compile::{Config, ExecutionReport},
         runj,
     },
@@ -22,7 +21,7 @@ use tracing::{error, info, instrument, warn};
 use triggered::Listener;
 
 use super::DEFAULT_MOUNT_DIRECTORY;
-use crate::{ActionContext, run_container::cache};
+use crate::{ActionContext, run_container::cache, shared};
 
 type CacheData = HashMap<String, Box<[u8]>>;
 
@@ -60,7 +59,7 @@ pub async fn execute(
                 for (file, data) in data {
                     let mut data = data.as_ref();
 
-                    let target = ctx.submission_root.join(file);
+                    let target = shared::file::resolve_path(&ctx.submission_root, &file)?;
                     let mut writer = BufWriter::new(
                         OpenOptions::new()
                             .create(true)
@@ -96,23 +95,19 @@ pub async fn execute(
             let mut run_container_config = config.run_container_config.clone();
             run_container_config.cwd = DEFAULT_MOUNT_DIRECTORY.to_owned();
 
-            run_container_config.mounts.push(run_container::MountConfig::Full(runj::MountConfig {
+            run_container_config.trusted_mounts.push(runj::MountConfig {
                 from: mount_directory.clone(),
                 to: DEFAULT_MOUNT_DIRECTORY.to_owned(),
                 options: None,
-            }));
-
-            run_container_config.mounts.extend(
-                config
-                    .sources
-                    .iter()
-                    .map(|file| runj::MountConfig {
-                        from: ctx.submission_root.join(&file.from_path),
-                        to: DEFAULT_MOUNT_DIRECTORY.join(&file.to_path),
-                        options: None,
-                    })
-                    .map(run_container::MountConfig::Full),
-            );
+            });
+
+            for file in &config.sources {
+                run_container_config.trusted_mounts.push(runj::MountConfig {
+                    from: shared::file::resolve_path(&ctx.submission_root, &file.from_path)?,
+                    to: DEFAULT_MOUNT_DIRECTORY.join(&file.to_path),
+                    options: None,
+                });
+            }
 
             run_container_config
         };
@@ -125,7 +120,7 @@ pub async fn execute(
 
             for file in &config.saves {
                 let source = mount_directory.join(file);
-                let target = ctx.submission_root.join(file);
+                let target = shared::file::resolve_path(&ctx.submission_root, file)?;
                 let metadata = fs::metadata(&source)
                     .await
                     .with_context(|| format!("The file {file} to save does not exist"))?;
@@ -202,7 +197,7 @@ async fn calculate_hash(submission_root: &Path, config: &Config) -> Result<Box<[
         hasher.update(&item.from_path);
         hasher.update(&item.to_path);
 
-        let mut file = File::open(submission_root.join(&item.from_path))
+        let mut file = File::open(shared::file::resolve_path(submission_root, &item.from_path)?)
             .await
             .context("Error opening the file")?;
 
@@ -220,7 +215,9 @@ async fn calculate_hash(submission_root: &Path, config: &Config) -> Result<Box<[
 mod tests {
     use std::path::Path;
 
-    use seele_shared::entities::run_container::{CommandConfig, run_judge::compile::CacheConfig};
+    use seele_shared::entities::run_container::{
+        self, CommandConfig, run_judge::compile::CacheConfig,
+    };
     use tokio::fs;
 
     use super::*;
@@ -236,6 +233,7 @@ mod tests {
                 fd: None,
                 paths: None,
                 mounts: vec![],
+                trusted_mounts: vec![],
                 limits: Default::default(),
             },
             sources: vec!["main.c".try_into().unwrap()],
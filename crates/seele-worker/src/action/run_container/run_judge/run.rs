@@ -3,14 +3,14 @@ use std::{fs::Permissions, os::unix::prelude::PermissionsExt};
 use anyhow::{Context, Result, bail};
 use seele_shared::entities::{
     ActionReportExt,
-    run_container::{self, run_judge::run::Config, runj},
+    run_container::{run_judge::run::Config, runj},
 };
 use tokio::fs;
 use tracing::{instrument, warn};
 use triggered::Listener;
 
 use super::DEFAULT_MOUNT_DIRECTORY;
-use crate::ActionContext;
+use crate::{ActionContext, shared};
 
 #[instrument(skip_all, name = "action_run_judge_run_execute")]
 pub async fn execute(
@@ -28,11 +28,11 @@ pub async fn execute(
 
         run_container_config.cwd = DEFAULT_MOUNT_DIRECTORY.to_owned();
 
-        run_container_config.mounts.push(run_container::MountConfig::Full(runj::MountConfig {
+        run_container_config.trusted_mounts.push(runj::MountConfig {
             from: mount_directory.clone(),
             to: DEFAULT_MOUNT_DIRECTORY.to_owned(),
             options: None,
-        }));
+        });
 
         if let Some(paths) = run_container_config.paths.as_mut() {
             paths.push(DEFAULT_MOUNT_DIRECTORY.to_owned());
@@ -41,13 +41,13 @@ pub async fn execute(
         }
 
         for file in &config.files {
-            let from_path = ctx.submission_root.join(&file.from_path);
+            let from_path = shared::file::resolve_path(&ctx.submission_root, &file.from_path)?;
 
             if let Err(err) = fs::metadata(&from_path).await {
                 bail!("The file {file} does not exist: {err:#}");
             }
 
-            run_container_config.mounts.push(run_container::MountConfig::Full({
+            run_container_config.trusted_mounts.push({
                 if file.exec {
                     fs::set_permissions(&from_path, Permissions::from_mode(0o777))
                         .await
@@ -61,7 +61,7 @@ pub async fn execute(
                 let options = if file.exec { Some(vec!["exec".to_owned()]) } else { None };
 
                 runj::MountConfig { from: from_path, to: to_path, options }
-            }));
+            });
         }
 
         crate::run_container::execute(handle, ctx, &run_container_config).await
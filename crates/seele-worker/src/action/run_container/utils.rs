@@ -1,4 +1,4 @@
-use std::{fs::Permissions, os::unix::prelude::PermissionsExt};
+use std::{fs::Permissions, os::unix::prelude::PermissionsExt, path::PathBuf};
 
 use anyhow::{Context, Result};
 use nix::mount;
@@ -54,19 +54,35 @@ pub async fn make_runj_config(ctx: &ActionContext, config: Config) -> Result<Run
 
     let command = config.command.try_into().context("Error parsing command")?;
 
-    let fd = config.fd.map(|fd| runj::FdConfig {
-        stdin: fd.stdin.map(|path| ctx.submission_root.join(path)),
-        stdout: fd.stdout.map(|path| ctx.submission_root.join(path)),
-        stderr: fd.stderr.map(|path| ctx.submission_root.join(path)),
-        ..fd
-    });
+    let fd = match config.fd {
+        None => None,
+        Some(fd) => {
+            let resolve = |path: Option<PathBuf>| {
+                path.map(|path| shared::file::resolve_path(&ctx.submission_root, &path))
+                    .transpose()
+                    .context("Error resolving the fd path")
+            };
+            Some(runj::FdConfig {
+                stdin: resolve(fd.stdin)?,
+                stdout: resolve(fd.stdout)?,
+                stderr: resolve(fd.stderr)?,
+                ..fd
+            })
+        }
+    };
 
-    let mounts = config
+    let mut mounts = config
         .mounts
         .into_iter()
-        .map(|item| item.into_runj_mount(&ctx.submission_root))
+        .map(|item| {
+            item.into_runj_mount(
+                &ctx.submission_root,
+                &conf::CONFIG.worker.action.run_container.mount_allowlist,
+            )
+        })
         .collect::<Result<Vec<runj::MountConfig>, _>>()
         .context("Error parsing mount")?;
+    mounts.extend(config.trusted_mounts);
 
     Ok(runj::RunjConfig {
         user_namespace,
@@ -1,6 +1,6 @@
 use std::{path::PathBuf, sync::Arc};
 
-use anyhow::{Result, bail};
+use anyhow::{Result, anyhow, bail};
 use chrono::Utc;
 use futures_util::{TryFutureExt, future};
 use seele_cgroup as cgroup;
@@ -10,11 +10,9 @@ use seele_shared::{
     entities::{
         ActionFailedReport, ActionReport, ActionReportExt, ActionSuccessReport, ActionTaskConfig,
     },
+    priority::{self, Priority},
 };
-use tokio::{
-    sync::{mpsc, oneshot},
-    time::Instant,
-};
+use tokio::{sync::oneshot, time::Instant};
 use tokio_graceful_shutdown::SubsystemHandle;
 use tracing::*;
 use triggered::Listener;
@@ -28,12 +26,14 @@ pub struct WorkerQueueItem {
     pub parent_span: Span,
     pub submission_id: String,
     pub submission_root: PathBuf,
+    pub priority: Priority,
     pub config: Arc<ActionTaskConfig>,
+    pub abort: Listener,
     pub report_tx: oneshot::Sender<Result<ActionReport>>,
 }
 
-pub type WorkerQueueTx = mpsc::Sender<WorkerQueueItem>;
-pub type WorkerQueueRx = mpsc::Receiver<WorkerQueueItem>;
+pub type WorkerQueueTx = priority::PrioritySender<WorkerQueueItem>;
+pub type WorkerQueueRx = priority::PriorityReceiver<WorkerQueueItem>;
 
 pub async fn worker_bootstrap(handle: SubsystemHandle, tx: oneshot::Sender<bool>) -> Result<()> {
     action::run_container::cache::init();
@@ -87,18 +87,8 @@ pub async fn worker_main(handle: SubsystemHandle, mut queue_rx: WorkerQueueRx) -
             item = queue_rx.recv() => match item {
                 None => break,
                 Some(item) => {
-                    tokio::spawn({
-                        let abort_handle = abort_handle.clone();
-                        let span = info_span!(parent: item.parent_span, "worker_handle_submission");
-                        async move {
-                            let report = execute_action(abort_handle.clone(), item.submission_root, &item.config).await;
-
-                            if item.report_tx.send(report).is_err() {
-                                error!(submission_id = item.submission_id, "Error sending the report");
-                            }
-                        }
-                        .instrument(span)
-                    });
+                    let span = info_span!(parent: &item.parent_span, "worker_handle_submission");
+                    tokio::spawn(handle_item(abort_handle.clone(), item).instrument(span));
                 }
             }
         }
@@ -107,12 +97,48 @@ pub async fn worker_main(handle: SubsystemHandle, mut queue_rx: WorkerQueueRx) -
     Ok(())
 }
 
+async fn handle_item(abort_handle: Listener, item: WorkerQueueItem) {
+    let report = if item.abort.is_triggered() {
+        // The submission has been aborted while the action was queued
+        Err(anyhow!(shared::SUBMISSION_ABORTED_MESSAGE))
+    } else {
+        // Aborts the action when either the worker shuts down or the submission is
+        // aborted, e.g. its deadline has expired
+        let (trigger, abort) = triggered::trigger();
+        let forward_abort = {
+            let submission_abort = item.abort.clone();
+            async move {
+                tokio::select! {
+                    _ = abort_handle => {},
+                    _ = submission_abort => {},
+                }
+                trigger.trigger();
+                future::pending::<()>().await
+            }
+        };
+
+        let report = tokio::select! {
+            report = execute_action(abort, item.submission_root, item.priority, &item.config) => report,
+            _ = forward_abort => unreachable!(),
+        };
+        match report {
+            Err(_) if item.abort.is_triggered() => Err(anyhow!(shared::SUBMISSION_ABORTED_MESSAGE)),
+            report => report,
+        }
+    };
+
+    if item.report_tx.send(report).is_err() {
+        error!(submission_id = item.submission_id, "Error sending the report");
+    }
+}
+
 async fn execute_action(
     handle: Listener,
     submission_root: PathBuf,
+    priority: Priority,
     task: &ActionTaskConfig,
 ) -> Result<ActionReport> {
-    let ctx = Arc::new(ActionContext { submission_root });
+    let ctx = Arc::new(ActionContext { submission_root, priority });
 
     let begin = Instant::now();
     let run_at = Utc::now();
@@ -137,11 +163,17 @@ async fn execute_action(
     };
 
     Ok(match ext {
-        ActionReportExt::Success(ext) => {
-            ActionReport::Success(ActionSuccessReport { run_at, time_elapsed_ms, ext })
-        }
-        ActionReportExt::Failure(ext) => {
-            ActionReport::Failed(ActionFailedReport { run_at, time_elapsed_ms, ext })
-        }
+        ActionReportExt::Success(ext) => ActionReport::Success(ActionSuccessReport {
+            run_at,
+            time_elapsed_ms,
+            previous_attempts: vec![],
+            ext,
+        }),
+        ActionReportExt::Failure(ext) => ActionReport::Failed(ActionFailedReport {
+            run_at,
+            time_elapsed_ms,
+            previous_attempts: vec![],
+            ext,
+        }),
     })
 }
@@ -1,6 +1,12 @@
 #![allow(dead_code)]
 
-use std::{fs::create_dir_all, time::Duration};
+use std::{
+    env,
+    fs::{create_dir_all, read_to_string},
+    io::{self, Read},
+    process::ExitCode,
+    time::Duration,
+};
 
 use anyhow::{Context, Result, bail};
 use opentelemetry::global;
@@ -9,24 +15,29 @@ use seele_config as conf;
 use seele_exchange as exchange;
 use seele_shared as shared;
 use seele_worker as worker;
-use tokio::{
-    runtime,
-    sync::{mpsc, oneshot},
-    task::spawn_blocking,
-    time::sleep,
-};
+use tokio::{runtime, sync::oneshot, task::spawn_blocking, time::sleep};
 use tokio_graceful_shutdown::{
     SubsystemBuilder, SubsystemHandle, Toplevel, errors::SubsystemError,
 };
 use tracing::*;
 
-use crate::{conf::SeeleWorkMode, worker::action};
+use crate::{conf::SeeleWorkMode, shared::priority, worker::action};
 
 mod cgroup;
 mod healthz;
 mod telemetry;
 
-fn main() {
+fn main() -> ExitCode {
+    let mut args = env::args().skip(1);
+    match args.next().as_deref() {
+        Some("validate") => return validate(args.next()),
+        Some("schema") => {
+            println!("{}", serde_json::to_string_pretty(&composer::submission_schema()).unwrap());
+            return ExitCode::SUCCESS;
+        }
+        _ => {}
+    }
+
     let runtime = runtime::Builder::new_current_thread()
         .max_blocking_threads(conf::CONFIG.thread_counts.worker + conf::CONFIG.thread_counts.runner)
         .thread_keep_alive(Duration::from_secs(u64::MAX))
@@ -77,6 +88,42 @@ fn main() {
             anyhow::Ok(())
         })
         .unwrap();
+
+    ExitCode::SUCCESS
+}
+
+/// Validates the submission read from the file, or the stdin if no file is
+/// specified, and prints the resolved tasks
+fn validate(path: Option<String>) -> ExitCode {
+    let config_yaml = match path {
+        Some(path) => read_to_string(&path)
+            .with_context(|| format!("Error reading the submission file: {path}")),
+        None => {
+            let mut config_yaml = String::new();
+            io::stdin()
+                .read_to_string(&mut config_yaml)
+                .context("Error reading the submission from stdin")
+                .map(|_| config_yaml)
+        }
+    };
+    let config_yaml = match config_yaml {
+        Ok(config_yaml) => config_yaml,
+        Err(err) => {
+            eprintln!("{err:#}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let validation = composer::validate_submission(&config_yaml);
+    match serde_json::to_string_pretty(&validation) {
+        Ok(json) => println!("{json}"),
+        Err(err) => {
+            eprintln!("Error serializing the validation result: {err:#}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if validation.is_valid() { ExitCode::SUCCESS } else { ExitCode::FAILURE }
 }
 
 async fn seele(handle: SubsystemHandle) -> Result<()> {
@@ -93,8 +140,9 @@ async fn seele(handle: SubsystemHandle) -> Result<()> {
 
     info!("Initializing seele components");
 
-    let (composer_queue_tx, composer_queue_rx) = mpsc::channel(conf::CONFIG.thread_counts.runner);
-    let (worker_queue_tx, worker_queue_rx) = mpsc::channel(conf::CONFIG.thread_counts.runner * 4);
+    let aging_interval = conf::CONFIG.priority.aging_interval();
+    let (composer_queue_tx, composer_queue_rx) = priority::channel("composer", aging_interval);
+    let (worker_queue_tx, worker_queue_rx) = priority::channel("worker", aging_interval);
 
     handle.start(SubsystemBuilder::new("healthz", healthz::healthz_main));
 
@@ -153,7 +201,13 @@ fn check_env() -> Result<()> {
     }
 
     info!("Creating necessary directories in {}", conf::PATHS.root.display());
-    for path in [&conf::PATHS.images, &conf::PATHS.submissions, &conf::PATHS.temp] {
+    for path in [
+        &conf::PATHS.images,
+        &conf::PATHS.submissions,
+        &conf::PATHS.journal,
+        &conf::PATHS.retained,
+        &conf::PATHS.temp,
+    ] {
         create_dir_all(path)
             .with_context(|| format!("Error creating the directory: {}", path.display()))?;
     }